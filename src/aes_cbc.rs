@@ -1,52 +1,79 @@
-use crate::pkcs7_pad;
-use aes::cipher::generic_array::typenum::Unsigned;
-use aes::{Aes128, BlockCipher, BlockDecrypt, BlockEncrypt, NewBlockCipher};
+use crate::modes;
+use crate::padding::Padding;
+use crate::{Error, PaddingError};
+use aes::{Aes128, NewBlockCipher};
 
-fn xor_in_place(a: &mut [u8], b: &[u8]) {
-    assert_eq!(a.len(), b.len());
-    a.iter_mut().zip(b.iter()).for_each(|(l, r)| *l = *l ^ *r);
-}
-
-const BLOCK_LEN: usize = <Aes128 as BlockCipher>::BlockSize::USIZE;
+pub use crate::modes::xor_in_place;
 
-// apply padding if it's the wrong length, I guess?
+/// Always applies PKCS#7 padding, so an empty or block-aligned message gains a full block
 pub fn encrypt(key: &[u8; 16], iv: &[u8; 16], msg: &[u8]) -> Vec<u8> {
-    if msg.len() == 0 {
-        return Vec::new();
-    }
+    encrypt_padded(key, iv, msg, Padding::Pkcs7).expect("PKCS#7 padding cannot fail")
+}
 
+pub fn encrypt_padded(
+    key: &[u8; 16],
+    iv: &[u8; 16],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
     let cipher = Aes128::new(key.into());
-
-    let mut result = msg.to_vec();
-    if result.len() % BLOCK_LEN != 0 {
-        pkcs7_pad(&mut result, BLOCK_LEN);
-    }
-    let mut chunks = result.chunks_exact_mut(BLOCK_LEN);
-    let mut last = chunks.next().unwrap();
-    xor_in_place(last, iv);
-    cipher.encrypt_block(last.into());
-    for chunk in chunks {
-        xor_in_place(chunk, last);
-        cipher.encrypt_block(chunk.into());
-        last = chunk;
-    }
-    result
+    modes::cbc::encrypt_padded(&cipher, iv, msg, padding)
 }
 
-/// does not strip padding
+/// does not strip padding. Panics if `msg` isn't a whole number of blocks.
 pub fn decrypt(key: &[u8; 16], iv: &[u8; 16], msg: &[u8]) -> Vec<u8> {
-    assert_eq!(0, msg.len() % BLOCK_LEN);
-    if msg.len() == 0 {
-        return Vec::new();
-    }
+    try_decrypt(key, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt(key: &[u8; 16], iv: &[u8; 16], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let cipher = Aes128::new(key.into());
+    modes::cbc::try_decrypt(&cipher, iv, msg)
+}
 
+pub fn decrypt_padded(
+    key: &[u8; 16],
+    iv: &[u8; 16],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
     let cipher = Aes128::new(key.into());
+    modes::cbc::decrypt_padded(&cipher, iv, msg, padding)
+}
+
+/// strips PKCS#7 padding, returning an error if it is invalid
+pub fn decrypt_and_unpad(
+    key: &[u8; 16],
+    iv: &[u8; 16],
+    msg: &[u8],
+) -> Result<Vec<u8>, PaddingError> {
+    decrypt_padded(key, iv, msg, Padding::Pkcs7)
+}
 
-    let prev_blocks = std::iter::once(&iv[..]).chain(msg.chunks_exact(BLOCK_LEN));
-    let mut result = msg.to_vec();
-    for (chunk, last) in result.chunks_exact_mut(BLOCK_LEN).zip(prev_blocks) {
-        cipher.decrypt_block(chunk.into());
-        xor_in_place(chunk, last);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8; 16] = b"YELLOW SUBMARINE";
+    const IV: [u8; 16] = [0; 16];
+
+    #[test]
+    fn test_aligned_round_trip() {
+        for msg in [&b""[..], b"YELLOW SUBMARINE", b"ICE ICE BABY"].iter() {
+            let ciphertext = encrypt(KEY, &IV, msg);
+            assert_eq!(ciphertext.len(), (msg.len() / 16 + 1) * 16);
+            assert_eq!(decrypt_and_unpad(KEY, &IV, &ciphertext), Ok(msg.to_vec()));
+        }
     }
-    result
-}
\ No newline at end of file
+
+    #[test]
+    fn test_try_decrypt() {
+        assert!(matches!(
+            try_decrypt(KEY, &IV, b"not a whole block"),
+            Err(Error::NotBlockAligned { .. })
+        ));
+        let ciphertext = encrypt(KEY, &IV, b"ICE ICE BABY");
+        let plaintext = try_decrypt(KEY, &IV, &ciphertext).unwrap();
+        assert_eq!(plaintext, b"ICE ICE BABY\x04\x04\x04\x04");
+    }
+}
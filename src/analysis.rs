@@ -0,0 +1,241 @@
+//! Statistics for guessing the key length of a repeating-key cipher. Every byte of plaintext
+//! encrypted with the same key byte keeps its frequency, so text split by the right key length
+//! looks like the language again while the wrong lengths look closer to random.
+
+use std::collections::HashMap;
+
+/// Chance that two bytes picked from uniformly random data are equal
+pub const RANDOM_IOC: f64 = 1.0 / 256.0;
+/// Chance that two bytes picked from English prose are equal, as measured on Moby Dick with
+/// case and punctuation kept
+pub const ENGLISH_IOC: f64 = 0.0613;
+
+/// Chance that two bytes picked from different positions of `data` are equal. 0 for less than
+/// two bytes.
+pub fn index_of_coincidence(data: &[u8]) -> f64 {
+    if data.len() < 2 {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    let matches: u64 = counts.iter().map(|&n| n * n.saturating_sub(1)).sum();
+    let len = data.len() as u64;
+    matches as f64 / (len * (len - 1)) as f64
+}
+
+/// Average index of coincidence of the `key_len` columns of `data`, i.e. of the bytes each key
+/// byte would have encrypted. 0 if `key_len` is 0.
+pub fn column_coincidence(data: &[u8], key_len: usize) -> f64 {
+    if key_len == 0 {
+        return 0.0;
+    }
+    let total: f64 = (0..key_len.min(data.len()))
+        .map(|i| {
+            let column: Vec<u8> = data[i..].iter().copied().step_by(key_len).collect();
+            index_of_coincidence(&column)
+        })
+        .sum();
+    total / key_len as f64
+}
+
+/// Friedman's estimate of the key length from the index of coincidence of the whole ciphertext.
+/// `language_ioc` is the index of coincidence of the plaintext language, e.g. [`ENGLISH_IOC`].
+/// Assumes different key bytes make unrelated columns, which holds for random keys but not for
+/// text keys, where it underestimates. Only a rough guide, and may be negative or infinite when
+/// the ciphertext doesn't fit the model.
+pub fn friedman(data: &[u8], language_ioc: f64) -> f64 {
+    let len = data.len() as f64;
+    let ioc = index_of_coincidence(data);
+    len * (language_ioc - RANDOM_IOC) / ((len - 1.0) * ioc - len * RANDOM_IOC + language_ioc)
+}
+
+/// Distances between each repeated `n`-byte sequence and its previous occurrence. Repeats in
+/// the ciphertext are mostly the same plaintext encrypted by the same part of the key, so the
+/// key length usually divides these. Empty if `n` is 0.
+pub fn kasiski_spacings(data: &[u8], n: usize) -> Vec<usize> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut spacings = Vec::new();
+    for (i, gram) in data.windows(n).enumerate() {
+        if let Some(prev) = last_seen.insert(gram, i) {
+            spacings.push(i - prev);
+        }
+    }
+    spacings
+}
+
+/// Greatest common divisor; `gcd(a, 0)` is `a`
+pub fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// How often each value comes up as the GCD of a pair of Kasiski spacings. The textbook estimate
+/// is the GCD of all the spacings, but one coincidental repeat is enough to drag that down to 1.
+/// Pairs of genuine spacings mostly have the key length itself as their GCD, so it comes out as
+/// the most common value instead. Looks at every pair, so quadratic in the number of spacings.
+pub fn spacing_gcds(spacings: &[usize]) -> HashMap<usize, usize> {
+    let mut counts = HashMap::new();
+    for (i, &a) in spacings.iter().enumerate() {
+        for &b in spacings[i + 1..].iter() {
+            *counts.entry(gcd(a, b)).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Fraction of the pairs counted by [`spacing_gcds`] whose GCD is exactly `key_len`. 0 if there
+/// are no pairs.
+pub fn kasiski_gcd_score(gcds: &HashMap<usize, usize>, key_len: usize) -> f64 {
+    let pairs: usize = gcds.values().sum();
+    if pairs == 0 {
+        return 0.0;
+    }
+    *gcds.get(&key_len).unwrap_or(&0) as f64 / pairs as f64
+}
+
+/// Fraction of the Kasiski spacings that `key_len` divides, less the fraction chance alone would
+/// give. An alternative to [`kasiski_gcd_score`] that needs no pairing; without the correction
+/// the plain fraction would favour short lengths. 0 if there are no spacings, or `key_len` is 0.
+pub fn kasiski_score(spacings: &[usize], key_len: usize) -> f64 {
+    if spacings.is_empty() || key_len == 0 {
+        return 0.0;
+    }
+    let divisible = spacings
+        .iter()
+        .filter(|&&s| s.is_multiple_of(key_len))
+        .count();
+    divisible as f64 / spacings.len() as f64 - 1.0 / key_len as f64
+}
+
+/// Fraction of bytes equal to the byte `shift` positions later. Peaks when `shift` is a
+/// multiple of the key length. 0 if `shift` is 0 or not less than the length of `data`.
+pub fn autocorrelation(data: &[u8], shift: usize) -> f64 {
+    if shift == 0 || shift >= data.len() {
+        return 0.0;
+    }
+    let matches = data
+        .iter()
+        .zip(data[shift..].iter())
+        .filter(|(a, b)| a == b)
+        .count();
+    matches as f64 / (data.len() - shift) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repeating_key_xor;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::fs;
+
+    fn moby_dick() -> Vec<u8> {
+        fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/MobyDick.txt")).unwrap()
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(b""), 0.0);
+        assert_eq!(index_of_coincidence(b"a"), 0.0);
+        assert_eq!(index_of_coincidence(b"aaaa"), 1.0);
+        assert_eq!(index_of_coincidence(b"abcd"), 0.0);
+        // 2 matching pairs out of 12
+        assert!((index_of_coincidence(b"aabb") - 1.0 / 3.0).abs() < 1e-12);
+
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(index_of_coincidence(&all), 0.0);
+        let ioc = index_of_coincidence(&moby_dick());
+        assert!((ioc - ENGLISH_IOC).abs() < 1e-3, "{}", ioc);
+    }
+
+    #[test]
+    fn test_column_coincidence() {
+        let text = &moby_dick()[50_000..53_000];
+        let ciphertext = repeating_key_xor(text, b"WHITE WHALE");
+        let right = column_coincidence(&ciphertext, 11);
+        assert!((right - ENGLISH_IOC).abs() < 0.02, "{}", right);
+        assert!(column_coincidence(&ciphertext, 7) < right / 2.0);
+        // a single byte per column has nothing to compare with
+        assert_eq!(column_coincidence(b"abc", 3), 0.0);
+        assert_eq!(column_coincidence(b"abc", 0), 0.0);
+    }
+
+    #[test]
+    fn test_friedman() {
+        let text = &moby_dick()[50_000..60_000];
+        let mut rng = StdRng::seed_from_u64(2);
+        for &key_len in [3, 6, 16, 29].iter() {
+            let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+            let estimate = friedman(&repeating_key_xor(text, &key), ENGLISH_IOC);
+            assert!(
+                (estimate - key_len as f64).abs() < key_len as f64 / 4.0,
+                "{}: {}",
+                key_len,
+                estimate
+            );
+        }
+        // letters XOR letters is usually small, and so is uppercase XOR uppercase
+        let estimate = friedman(&repeating_key_xor(text, b"YELLOW SUBMARINE"), ENGLISH_IOC);
+        assert!(estimate < 8.0, "{}", estimate);
+        // the plaintext itself looks like a key length of 1
+        assert!((friedman(text, ENGLISH_IOC) - 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_kasiski() {
+        assert_eq!(kasiski_spacings(b"abcXXabcYYYabc", 3), vec![5, 6]);
+        assert_eq!(kasiski_spacings(b"aaaa", 2), vec![1, 1]);
+        assert!(kasiski_spacings(b"abcdef", 3).is_empty());
+        assert!(kasiski_spacings(b"aaaa", 0).is_empty());
+
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(7, 0), 7);
+
+        let gcds = spacing_gcds(&[6, 9, 10, 12]);
+        assert_eq!(gcds.values().sum::<usize>(), 6);
+        assert_eq!(gcds[&3], 2);
+        assert_eq!(gcds[&6], 1);
+        assert_eq!(kasiski_gcd_score(&gcds, 3), 2.0 / 6.0);
+        assert_eq!(kasiski_gcd_score(&gcds, 5), 0.0);
+        assert_eq!(kasiski_gcd_score(&spacing_gcds(&[6]), 6), 0.0);
+
+        assert_eq!(kasiski_score(&[], 3), 0.0);
+        assert!((kasiski_score(&[6, 9, 10], 3) - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(kasiski_score(&[6, 9, 10], 1), 0.0);
+        assert_eq!(kasiski_score(&[6, 9, 10], 0), 0.0);
+
+        let text = &moby_dick()[50_000..53_000];
+        let spacings = kasiski_spacings(&repeating_key_xor(text, b"WHITE WHALE"), 3);
+        let best = (2..=40)
+            .max_by(|&a, &b| kasiski_score(&spacings, a).total_cmp(&kasiski_score(&spacings, b)))
+            .unwrap();
+        assert_eq!(best, 11);
+        let gcds = spacing_gcds(&spacings);
+        let best = (2..=40)
+            .max_by(|&a, &b| kasiski_gcd_score(&gcds, a).total_cmp(&kasiski_gcd_score(&gcds, b)))
+            .unwrap();
+        assert_eq!(best, 11);
+    }
+
+    #[test]
+    fn test_autocorrelation() {
+        assert_eq!(autocorrelation(b"abcabcabc", 0), 0.0);
+        assert_eq!(autocorrelation(b"abcabcabc", 3), 1.0);
+        assert_eq!(autocorrelation(b"abcabcabc", 1), 0.0);
+        assert_eq!(autocorrelation(b"abc", 3), 0.0);
+
+        let text = &moby_dick()[50_000..53_000];
+        let ciphertext = repeating_key_xor(text, b"WHITE WHALE");
+        assert!(autocorrelation(&ciphertext, 11) > 2.0 * autocorrelation(&ciphertext, 10));
+        assert!(autocorrelation(&ciphertext, 22) > 2.0 * autocorrelation(&ciphertext, 21));
+    }
+}
@@ -0,0 +1,9 @@
+//! Attacks against the primitives in this crate, written against oracles so they can be pointed
+//! at local stand-ins for real services.
+
+pub mod byte_at_a_time;
+pub mod cbc_bitflip;
+pub mod length_extension;
+pub mod mt_clone;
+pub mod padding_oracle;
+pub mod timing_leak;
@@ -0,0 +1,290 @@
+//! Byte-at-a-time ECB decryption. Recovers `secret` from an oracle computing
+//! `ECB(prefix || attacker input || secret)` under a fixed unknown key, where `prefix` is fixed
+//! but of unknown length. The oracle is expected to apply PKCS#7 padding.
+
+use crate::{adjacent_repeated_blocks, detect_ecb};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// Longest block length we try before giving up
+const MAX_BLOCK_LEN: usize = 64;
+
+/// Something that encrypts attacker-controlled input. Implemented for closures, and usable as a
+/// trait object.
+pub trait EncryptOracle {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> EncryptOracle for F {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AttackError {
+    BlockLenNotFound,
+    NotEcb,
+    PrefixNotFound,
+    SecretLenNotFound,
+    /// no byte value reproduced the target block, so the oracle isn't deterministic
+    NoMatch {
+        position: usize,
+    },
+}
+
+impl Display for AttackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttackError::BlockLenNotFound => write!(f, "could not determine the block length"),
+            AttackError::NotEcb => write!(f, "oracle does not appear to use ECB"),
+            AttackError::PrefixNotFound => write!(f, "could not determine the prefix length"),
+            AttackError::SecretLenNotFound => write!(f, "could not determine the secret length"),
+            AttackError::NoMatch { position } => {
+                write!(f, "no byte matched at secret position {}", position)
+            }
+        }
+    }
+}
+
+impl error::Error for AttackError {}
+
+#[derive(Debug)]
+pub struct Recovered {
+    pub secret: Vec<u8>,
+    pub block_len: usize,
+    pub prefix_len: usize,
+    /// total number of times the oracle was called
+    pub queries: usize,
+}
+
+struct Counting<'a, O: ?Sized> {
+    oracle: &'a mut O,
+    queries: usize,
+}
+
+impl<'a, O: EncryptOracle + ?Sized> Counting<'a, O> {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        self.oracle.encrypt(input)
+    }
+}
+
+pub fn recover_secret<O: EncryptOracle + ?Sized>(oracle: &mut O) -> Result<Recovered, AttackError> {
+    let mut oracle = Counting { oracle, queries: 0 };
+
+    let block_len = find_block_len(&mut oracle)?;
+    if !detect_ecb(&oracle.encrypt(&vec![b'A'; 3 * block_len]), block_len) {
+        return Err(AttackError::NotEcb);
+    }
+    let prefix_len = find_prefix_len(&mut oracle, block_len)?;
+
+    // pad the prefix out to a block boundary; everything we control starts at `start`
+    let align = vec![b'A'; (block_len - prefix_len % block_len) % block_len];
+    let start = prefix_len + align.len();
+
+    // the ciphertext grows by a block once the input fills the final padding block exactly
+    let base_len = oracle.encrypt(&align).len();
+    let mut secret_len = None;
+    for extra in 1..=block_len {
+        let mut input = align.clone();
+        input.resize(align.len() + extra, b'A');
+        if oracle.encrypt(&input).len() > base_len {
+            // the oracle's output lengths don't add up otherwise
+            secret_len = base_len.checked_sub(start + extra);
+            break;
+        }
+    }
+    let secret_len = secret_len.ok_or(AttackError::SecretLenNotFound)?;
+
+    // each byte of secret sits at the end of a block when we add this many filler bytes. There
+    // are only `block_len` distinct fillers, so encrypt each once.
+    let targets: Vec<Vec<u8>> = (0..block_len)
+        .map(|i| {
+            let mut input = align.clone();
+            input.resize(align.len() + block_len - 1 - i, b'A');
+            oracle.encrypt(&input)
+        })
+        .collect();
+
+    let mut known = vec![b'A'; block_len - 1];
+    for i in 0..secret_len {
+        let block_start = start + (i / block_len) * block_len;
+        let target = targets[i % block_len]
+            .get(block_start..block_start + block_len)
+            .ok_or(AttackError::NoMatch { position: i })?;
+
+        let mut input = align.clone();
+        input.extend_from_slice(&known[known.len() - (block_len - 1)..]);
+        input.push(0);
+        let found = (0..=255).find(|&c| {
+            *input.last_mut().unwrap() = c;
+            oracle.encrypt(&input).get(start..start + block_len) == Some(target)
+        });
+        match found {
+            Some(c) => known.push(c),
+            // the oracle's output changed under us
+            None => return Err(AttackError::NoMatch { position: i }),
+        }
+    }
+
+    let secret = known.split_off(block_len - 1);
+    Ok(Recovered {
+        secret,
+        block_len,
+        prefix_len,
+        queries: oracle.queries,
+    })
+}
+
+fn find_block_len<O: EncryptOracle + ?Sized>(
+    oracle: &mut Counting<O>,
+) -> Result<usize, AttackError> {
+    let base_len = oracle.encrypt(&[]).len();
+    (1..=MAX_BLOCK_LEN)
+        .map(|n| oracle.encrypt(&vec![b'A'; n]).len())
+        .find(|&len| len > base_len)
+        .map(|len| len - base_len)
+        .ok_or(AttackError::BlockLenNotFound)
+}
+
+/// Adds filler until two adjacent blocks of our input encrypt identically. Runs with two
+/// different filler bytes and requires both to agree, so repeats in the prefix or bytes of
+/// the prefix that happen to equal the filler aren't mistaken for our input.
+fn find_prefix_len<O: EncryptOracle + ?Sized>(
+    oracle: &mut Counting<O>,
+    block_len: usize,
+) -> Result<usize, AttackError> {
+    for pad in 0..block_len {
+        let a = oracle.encrypt(&vec![b'A'; pad + 2 * block_len]);
+        let b = oracle.encrypt(&vec![b'B'; pad + 2 * block_len]);
+        let repeats_b = adjacent_repeated_blocks(&b, block_len);
+        let found = adjacent_repeated_blocks(&a, block_len)
+            .into_iter()
+            .filter(|i| repeats_b.contains(i))
+            .find(|&i| {
+                let block = i * block_len..(i + 1) * block_len;
+                a[block.clone()] != b[block]
+            });
+        // a repeat that starts inside the filler means the oracle isn't what we think it is
+        if let Some(prefix_len) = found.and_then(|i| (i * block_len).checked_sub(pad)) {
+            return Ok(prefix_len);
+        }
+    }
+    Err(AttackError::PrefixNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::{cbc, ecb};
+    use crate::padding::Padding;
+    use aes::{Aes128, NewBlockCipher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const SECRET: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkg\
+        aGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBq\
+        dXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUg\
+        YnkK";
+
+    fn ecb_oracle(rng: &mut StdRng, prefix_len: usize) -> impl FnMut(&[u8]) -> Vec<u8> {
+        let cipher = Aes128::new(&rng.gen::<[u8; 16]>().into());
+        let prefix: Vec<u8> = (0..prefix_len).map(|_| rng.gen()).collect();
+        let secret = base64::decode(SECRET).unwrap();
+        move |input: &[u8]| {
+            let msg = [&prefix[..], input, &secret].concat();
+            ecb::encrypt_padded(&cipher, &msg, Padding::Pkcs7).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_no_prefix() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut oracle = ecb_oracle(&mut rng, 0);
+        let recovered = recover_secret(&mut oracle).unwrap();
+        assert_eq!(recovered.secret, base64::decode(SECRET).unwrap());
+        assert_eq!(recovered.block_len, 16);
+        assert_eq!(recovered.prefix_len, 0);
+        assert!(recovered.queries > recovered.secret.len());
+    }
+
+    #[test]
+    fn test_random_prefix() {
+        let mut rng = StdRng::seed_from_u64(14);
+        for prefix_len in [1, 5, 15, 16, 17, 40].iter().copied() {
+            let mut oracle = ecb_oracle(&mut rng, prefix_len);
+            let oracle: &mut dyn EncryptOracle = &mut oracle;
+            let recovered = recover_secret(oracle).unwrap();
+            assert_eq!(recovered.secret, base64::decode(SECRET).unwrap());
+            assert_eq!(recovered.prefix_len, prefix_len);
+        }
+    }
+
+    #[test]
+    fn test_prefix_ending_in_filler() {
+        let cipher = Aes128::new(b"YELLOW SUBMARINE".into());
+        let mut oracle = |input: &[u8]| {
+            let msg = [&b"prefixAAA"[..], input, b"attack at dawn"].concat();
+            ecb::encrypt_padded(&cipher, &msg, Padding::Pkcs7).unwrap()
+        };
+        let recovered = recover_secret(&mut oracle).unwrap();
+        assert_eq!(recovered.prefix_len, 9);
+        assert_eq!(recovered.secret, b"attack at dawn");
+    }
+
+    #[test]
+    fn test_changing_key() {
+        // a fresh key every call keeps the lengths and the ECB repeats, but nothing ever matches
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut oracle = |input: &[u8]| {
+            let cipher = Aes128::new(&rng.gen::<[u8; 16]>().into());
+            let msg = [input, b"attack at dawn"].concat();
+            ecb::encrypt_padded(&cipher, &msg, Padding::Pkcs7).unwrap()
+        };
+        let err = recover_secret(&mut oracle).unwrap_err();
+        assert_eq!(err, AttackError::NoMatch { position: 0 });
+        assert_eq!(err.to_string(), "no byte matched at secret position 0");
+    }
+
+    #[test]
+    fn test_inconsistent_lengths() {
+        // lengths that can't come from encrypting input || secret mustn't panic
+        let cipher = Aes128::new(b"YELLOW SUBMARINE".into());
+        let mut empty_for_no_input = |input: &[u8]| {
+            if input.is_empty() {
+                return Vec::new();
+            }
+            ecb::encrypt_padded(&cipher, input, Padding::Pkcs7).unwrap()
+        };
+        assert_eq!(
+            recover_secret(&mut empty_for_no_input).unwrap_err(),
+            AttackError::SecretLenNotFound
+        );
+
+        let mut short_target = |input: &[u8]| {
+            if input.len() == 15 {
+                return Vec::new();
+            }
+            let msg = [input, b"attack at dawn"].concat();
+            ecb::encrypt_padded(&cipher, &msg, Padding::Pkcs7).unwrap()
+        };
+        assert_eq!(
+            recover_secret(&mut short_target).unwrap_err(),
+            AttackError::NoMatch { position: 0 }
+        );
+    }
+
+    #[test]
+    fn test_cbc_rejected() {
+        let cipher = Aes128::new(b"YELLOW SUBMARINE".into());
+        let mut oracle = |input: &[u8]| {
+            let msg = [input, b"attack at dawn"].concat();
+            cbc::encrypt_padded(&cipher, &[0; 16], &msg, Padding::Pkcs7).unwrap()
+        };
+        assert_eq!(
+            recover_secret(&mut oracle).unwrap_err(),
+            AttackError::NotEcb
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! CBC bit flipping. Each plaintext block is XORed with the previous ciphertext block (or the
+//! IV) after decryption, so flipping bits there flips the same bits in the plaintext. The block
+//! that gets modified decrypts to garbage.
+
+use crate::aes_cbc::xor_in_place;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FlipError {
+    /// the ciphertext is not a whole number of IV-sized blocks
+    BadLength,
+    BlockOutOfRange,
+    /// `known` and `desired` differ in length, or are longer than a block
+    PlaintextLength,
+}
+
+impl Display for FlipError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            FlipError::BadLength => "ciphertext is not a whole number of blocks",
+            FlipError::BlockOutOfRange => "block index is past the end of the ciphertext",
+            FlipError::PlaintextLength => "known and desired plaintext must fit in one block",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl error::Error for FlipError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Flipped {
+    pub iv: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Returns a copy of `iv` and `ciphertext` modified so that the start of plaintext block
+/// `block_index`, currently `known`, decrypts to `desired` instead. Block 0 is changed through
+/// the IV.
+pub fn flip(
+    iv: &[u8],
+    ciphertext: &[u8],
+    block_index: usize,
+    known: &[u8],
+    desired: &[u8],
+) -> Result<Flipped, FlipError> {
+    let block_len = iv.len();
+    if block_len == 0 || !ciphertext.len().is_multiple_of(block_len) {
+        return Err(FlipError::BadLength);
+    }
+    if block_index >= ciphertext.len() / block_len {
+        return Err(FlipError::BlockOutOfRange);
+    }
+    if known.len() != desired.len() || known.len() > block_len {
+        return Err(FlipError::PlaintextLength);
+    }
+
+    let mut delta = known.to_vec();
+    xor_in_place(&mut delta, desired);
+
+    let mut iv = iv.to_vec();
+    let mut ciphertext = ciphertext.to_vec();
+    let target = match block_index {
+        0 => &mut iv[..known.len()],
+        i => {
+            let start = (i - 1) * block_len;
+            &mut ciphertext[start..start + known.len()]
+        }
+    };
+    xor_in_place(target, &delta);
+    Ok(Flipped { iv, ciphertext })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_cbc;
+
+    const KEY: &[u8; 16] = b"YELLOW SUBMARINE";
+    const IV: &[u8; 16] = b"ICE ICE BABY....";
+    const PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+    const SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+    /// quotes out ';' and '=' like a service building a cookie would
+    fn encrypt_userdata(userdata: &[u8]) -> Vec<u8> {
+        let mut msg = PREFIX.to_vec();
+        for &b in userdata {
+            match b {
+                b';' => msg.extend_from_slice(b"%3B"),
+                b'=' => msg.extend_from_slice(b"%3D"),
+                b => msg.push(b),
+            }
+        }
+        msg.extend_from_slice(SUFFIX);
+        aes_cbc::encrypt(KEY, IV, &msg)
+    }
+
+    fn is_admin(iv: &[u8], ciphertext: &[u8]) -> bool {
+        let mut iv_arr = [0; 16];
+        iv_arr.copy_from_slice(iv);
+        let plaintext = aes_cbc::decrypt_and_unpad(KEY, &iv_arr, ciphertext).unwrap();
+        plaintext
+            .split(|&b| b == b';')
+            .any(|field| field == b"admin=true")
+    }
+
+    #[test]
+    fn test_admin_injection() {
+        let known = b"AadminAtrueA";
+        let ciphertext = encrypt_userdata(known);
+        assert!(!is_admin(IV, &ciphertext));
+
+        // PREFIX is exactly two blocks, so userdata starts block 2
+        let flipped = flip(IV, &ciphertext, 2, known, b";admin=true;").unwrap();
+        assert_eq!(flipped.iv, IV);
+        assert!(is_admin(&flipped.iv, &flipped.ciphertext));
+    }
+
+    #[test]
+    fn test_flip_through_iv() {
+        let ciphertext = aes_cbc::encrypt(KEY, IV, b"role=user;uid=10");
+        let flipped = flip(IV, &ciphertext, 0, b"role=user;", b"role=root;").unwrap();
+        assert_eq!(flipped.ciphertext, ciphertext);
+
+        let mut iv = [0; 16];
+        iv.copy_from_slice(&flipped.iv);
+        assert_eq!(
+            aes_cbc::decrypt_and_unpad(KEY, &iv, &flipped.ciphertext),
+            Ok(b"role=root;uid=10".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let ciphertext = [0; 32];
+        assert_eq!(
+            flip(IV, &ciphertext[..20], 0, b"a", b"b"),
+            Err(FlipError::BadLength)
+        );
+        assert_eq!(
+            flip(IV, &ciphertext, 2, b"a", b"b"),
+            Err(FlipError::BlockOutOfRange)
+        );
+        assert_eq!(
+            flip(IV, &ciphertext, 1, b"a", b"bc"),
+            Err(FlipError::PlaintextLength)
+        );
+        assert_eq!(
+            flip(IV, &ciphertext, 1, &[0; 17], &[1; 17]),
+            Err(FlipError::PlaintextLength)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Length extension against secret-prefix MACs. A Merkle-Damgård digest is the hash's whole
+//! internal state, so from `H(key || msg)` an attacker can carry on hashing and get
+//! `H(key || msg || glue || suffix)`, where `glue` is the padding the hash added after
+//! `key || msg`. Only the key's length needs guessing. Works the same for every
+//! [`HashFunction`], SHA-1 and MD4 alike; only the byte order of the length in the glue differs.
+
+use crate::hash::HashFunction;
+pub use crate::mac::MacVerifier;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AttackError {
+    /// the MAC is not one digest long
+    BadMacLength { expected: usize, actual: usize },
+    /// the verifier rejected the forgery for every key length tried
+    NoKeyLength,
+}
+
+impl Display for AttackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttackError::BadMacLength { expected, actual } => {
+                write!(f, "MAC must be {} bytes, got {}", expected, actual)
+            }
+            AttackError::NoKeyLength => write!(f, "no key length in range worked"),
+        }
+    }
+}
+
+impl error::Error for AttackError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forgery {
+    /// the key length this forgery assumes
+    pub key_len: usize,
+    /// `msg || glue || suffix`
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+/// The padding `H` put after `key || msg`, which ends up in the middle of the forged message
+pub fn glue_padding<H: HashFunction>(key_len: usize, msg_len: usize) -> Vec<u8> {
+    H::padding((key_len + msg_len) as u64)
+}
+
+/// Extends `msg`, whose MAC is `mac`, with `suffix`, assuming the key is `key_len` bytes long
+pub fn extend<H: HashFunction>(
+    msg: &[u8],
+    mac: &[u8],
+    key_len: usize,
+    suffix: &[u8],
+) -> Result<Forgery, AttackError> {
+    if mac.len() != H::DIGEST_LEN {
+        return Err(AttackError::BadMacLength {
+            expected: H::DIGEST_LEN,
+            actual: mac.len(),
+        });
+    }
+    let mut message = msg.to_vec();
+    message.extend(glue_padding::<H>(key_len, msg.len()));
+    let processed = (key_len + message.len()) as u64;
+    // the length and alignment were both just checked or arranged
+    let mut hasher = H::from_state(mac, processed).expect("valid hash state");
+    hasher.update(suffix);
+    message.extend_from_slice(suffix);
+    Ok(Forgery {
+        key_len,
+        message,
+        mac: hasher.finalize(),
+    })
+}
+
+/// Tries each key length in `key_lens` until `verifier` accepts the forgery
+pub fn forge<H: HashFunction, V: MacVerifier + ?Sized>(
+    msg: &[u8],
+    mac: &[u8],
+    suffix: &[u8],
+    key_lens: RangeInclusive<usize>,
+    verifier: &V,
+) -> Result<Forgery, AttackError> {
+    for key_len in key_lens {
+        let forgery = extend::<H>(msg, mac, key_len, suffix)?;
+        if verifier.verify(&forgery.message, &forgery.mac) {
+            return Ok(forgery);
+        }
+    }
+    Err(AttackError::NoKeyLength)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::md4::Md4;
+    use crate::hash::sha1::Sha1;
+    use crate::mac::secret_prefix_mac;
+    use crate::oracle::SecretPrefixOracle;
+
+    const MSG: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    fn is_admin(msg: &[u8]) -> bool {
+        msg.split(|&b| b == b';')
+            .any(|field| field == b"admin=true")
+    }
+
+    #[test]
+    fn test_extend() {
+        let key = b"YELLOW SUBMARINE";
+        let mac = secret_prefix_mac::<Sha1>(key, MSG);
+        let forgery = extend::<Sha1>(MSG, &mac, key.len(), b";admin=true").unwrap();
+        assert!(forgery.message.starts_with(MSG));
+        assert!(is_admin(&forgery.message));
+        assert_eq!(
+            forgery.mac,
+            secret_prefix_mac::<Sha1>(key, &forgery.message)
+        );
+
+        // the wrong key length puts the glue in the wrong place
+        let wrong = extend::<Sha1>(MSG, &mac, key.len() + 1, b";admin=true").unwrap();
+        assert_ne!(wrong.mac, secret_prefix_mac::<Sha1>(key, &wrong.message));
+
+        assert_eq!(
+            extend::<Sha1>(MSG, &mac[..16], 16, b""),
+            Err(AttackError::BadMacLength {
+                expected: 20,
+                actual: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_forge_against_oracle() {
+        for seed in 0..8 {
+            let oracle = SecretPrefixOracle::<Sha1>::with_seed(seed);
+            let mac = oracle.sign(MSG);
+            let forgery = forge::<Sha1, _>(MSG, &mac, b";admin=true", 0..=64, &oracle).unwrap();
+            assert!(oracle.verify(&forgery.message, &forgery.mac));
+            assert!(is_admin(&forgery.message));
+        }
+    }
+
+    #[test]
+    fn test_md4() {
+        let key = b"YELLOW SUBMARINE";
+        let mac = secret_prefix_mac::<Md4>(key, MSG);
+        let forgery = extend::<Md4>(MSG, &mac, key.len(), b";admin=true").unwrap();
+        assert_eq!(forgery.mac, secret_prefix_mac::<Md4>(key, &forgery.message));
+        // the glue ends with the bit length of key || msg, little-endian
+        let glue = glue_padding::<Md4>(key.len(), MSG.len());
+        assert_eq!(
+            &glue[glue.len() - 8..],
+            &(8 * (key.len() + MSG.len()) as u64).to_le_bytes()
+        );
+        assert_ne!(glue, glue_padding::<Sha1>(key.len(), MSG.len()));
+
+        for seed in 0..8 {
+            let oracle = SecretPrefixOracle::<Md4>::with_seed(seed);
+            let mac = oracle.sign(MSG);
+            let forgery = forge::<Md4, _>(MSG, &mac, b";admin=true", 0..=64, &oracle).unwrap();
+            assert!(oracle.verify(&forgery.message, &forgery.mac));
+            assert!(is_admin(&forgery.message));
+        }
+        assert!(matches!(
+            extend::<Md4>(MSG, &[0; 20], 16, b""),
+            Err(AttackError::BadMacLength {
+                expected: 16,
+                actual: 20
+            })
+        ));
+    }
+
+    #[test]
+    fn test_forge_out_of_range() {
+        let key = [0u8; 40];
+        let mac = secret_prefix_mac::<Sha1>(&key, MSG);
+        let verify = |msg: &[u8], mac: &[u8]| secret_prefix_mac::<Sha1>(&key, msg) == mac;
+        assert_eq!(
+            forge::<Sha1, _>(MSG, &mac, b";admin=true", 0..=39, &verify),
+            Err(AttackError::NoKeyLength)
+        );
+        assert_eq!(
+            forge::<Sha1, _>(MSG, &mac, b";admin=true", 40..=40, &verify)
+                .unwrap()
+                .key_len,
+            40
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Cloning a Mersenne Twister from its output. Untempering `N` consecutive outputs gives `N`
+//! consecutive state words, and the twist only ever looks at the last `N` words, so a generator
+//! holding them predicts everything that follows. The outputs need not start at a twist.
+
+use crate::mt19937::{untemper, untemper_64, Mt19937, Mt19937_64, N, N_64};
+use std::convert::TryInto;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CloneError {
+    TooFewOutputs {
+        needed: usize,
+        actual: usize,
+    },
+    /// the clone disagreed with an output after the first `N`, so they aren't consecutive
+    /// outputs of a plain Mersenne Twister
+    Mismatch {
+        index: usize,
+    },
+}
+
+impl Display for CloneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CloneError::TooFewOutputs { needed, actual } => {
+                write!(f, "need {} outputs, got {}", needed, actual)
+            }
+            CloneError::Mismatch { index } => {
+                write!(f, "clone disagrees with output {}", index)
+            }
+        }
+    }
+}
+
+impl error::Error for CloneError {}
+
+/// A generator that continues from the last of `outputs`. Needs at least [`N`]; any beyond that
+/// are checked against the clone.
+pub fn clone_from_outputs(outputs: &[u32]) -> Result<Mt19937, CloneError> {
+    if outputs.len() < N {
+        return Err(CloneError::TooFewOutputs {
+            needed: N,
+            actual: outputs.len(),
+        });
+    }
+    let state: Vec<u32> = outputs[..N].iter().map(|&y| untemper(y)).collect();
+    let mut clone = Mt19937::from_state(state.try_into().unwrap());
+    for (index, &output) in outputs.iter().enumerate().skip(N) {
+        if clone.next_u32() != output {
+            return Err(CloneError::Mismatch { index });
+        }
+    }
+    Ok(clone)
+}
+
+/// Like [`clone_from_outputs`], from at least [`N_64`] outputs of MT19937-64
+pub fn clone_from_outputs_64(outputs: &[u64]) -> Result<Mt19937_64, CloneError> {
+    if outputs.len() < N_64 {
+        return Err(CloneError::TooFewOutputs {
+            needed: N_64,
+            actual: outputs.len(),
+        });
+    }
+    let state: Vec<u64> = outputs[..N_64].iter().map(|&y| untemper_64(y)).collect();
+    let mut clone = Mt19937_64::from_state(state.try_into().unwrap());
+    for (index, &output) in outputs.iter().enumerate().skip(N_64) {
+        if clone.next_u64() != output {
+            return Err(CloneError::Mismatch { index });
+        }
+    }
+    Ok(clone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clone() {
+        // starting part way through a twist as well as right at one
+        for &skip in [0, 1, 300, N - 1, N, 5 * N + 17].iter() {
+            let mut target = Mt19937::new(1_600_000_000 + skip as u32);
+            for _ in 0..skip {
+                target.next_u32();
+            }
+            let outputs: Vec<u32> = (0..N).map(|_| target.next_u32()).collect();
+            let mut clone = clone_from_outputs(&outputs).unwrap();
+            for _ in 0..2 * N {
+                assert_eq!(clone.next_u32(), target.next_u32(), "skip {}", skip);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clone_64() {
+        for &skip in [0, 1, 100, N_64 - 1, N_64, 3 * N_64 + 5].iter() {
+            let mut target = Mt19937_64::new(0xdead_beef + skip as u64);
+            for _ in 0..skip {
+                target.next_u64();
+            }
+            let outputs: Vec<u64> = (0..N_64).map(|_| target.next_u64()).collect();
+            let mut clone = clone_from_outputs_64(&outputs).unwrap();
+            for _ in 0..2 * N_64 {
+                assert_eq!(clone.next_u64(), target.next_u64(), "skip {}", skip);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extra_outputs_checked() {
+        let mut target = Mt19937::from_key(&[1, 2, 3]);
+        let mut outputs: Vec<u32> = (0..N + 10).map(|_| target.next_u32()).collect();
+        let mut clone = clone_from_outputs(&outputs).unwrap();
+        assert_eq!(clone.next_u32(), target.next_u32());
+
+        outputs[N + 3] ^= 1;
+        assert_eq!(
+            clone_from_outputs(&outputs).err(),
+            Some(CloneError::Mismatch { index: N + 3 })
+        );
+        assert_eq!(
+            clone_from_outputs(&outputs[..N - 1]).err(),
+            Some(CloneError::TooFewOutputs {
+                needed: N,
+                actual: N - 1
+            })
+        );
+        assert_eq!(
+            clone_from_outputs_64(&[0; 10]).err(),
+            Some(CloneError::TooFewOutputs {
+                needed: N_64,
+                actual: 10
+            })
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Recovering a MAC from a verifier that compares it byte by byte and gives up at the first
+//! wrong one. Each correct leading byte makes rejection a little slower, so the MAC can be
+//! found one byte at a time by trying all 256 values and keeping the slowest.
+//!
+//! Timings are noisy, so every candidate is measured several times and summarized with a
+//! statistic that ignores outliers, and a position is measured again until one candidate
+//! clearly stands out. A wrong guess still gets through now and then; it shows up at the next
+//! position, where nothing is any slower than before, and the attack backs up a byte.
+
+use crate::mac::MacVerifier;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::time::Instant;
+
+/// How to summarize the timings of one candidate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statistic {
+    Median,
+    /// mean after dropping this fraction of the samples from each end, e.g. 0.2
+    TrimmedMean(f64),
+}
+
+impl Statistic {
+    /// 0 for no samples
+    pub fn apply(self, samples: &[f64]) -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        match self {
+            Statistic::Median if n.is_multiple_of(2) => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+            Statistic::Median => sorted[n / 2],
+            Statistic::TrimmedMean(fraction) => {
+                // always keep at least one sample
+                let cut = ((n as f64 * fraction) as usize).min((n - 1) / 2);
+                let kept = &sorted[cut..n - cut];
+                kept.iter().sum::<f64>() / kept.len() as f64
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingOptions {
+    /// timings per candidate byte per round
+    pub samples: usize,
+    pub statistic: Statistic,
+    /// most rounds spent on one position before settling for the best so far
+    pub max_rounds: usize,
+    /// how many times to back up over a wrong guess before giving up
+    pub max_backtracks: usize,
+}
+
+impl Default for TimingOptions {
+    fn default() -> Self {
+        TimingOptions {
+            samples: 5,
+            statistic: Statistic::Median,
+            max_rounds: 10,
+            max_backtracks: 20,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AttackError {
+    /// asked for a MAC of length 0
+    EmptyMac,
+    /// the guesses kept turning out wrong; the leak is too small for the options, or absent
+    NotFound,
+}
+
+impl Display for AttackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttackError::EmptyMac => write!(f, "MAC length must be positive"),
+            AttackError::NotFound => write!(f, "no MAC was accepted; the timings misled us"),
+        }
+    }
+}
+
+impl error::Error for AttackError {}
+
+/// Time one verification in nanoseconds
+fn time<V: MacVerifier + ?Sized>(verifier: &V, msg: &[u8], mac: &[u8]) -> f64 {
+    let start = Instant::now();
+    verifier.verify(msg, mac);
+    start.elapsed().as_nanos() as f64
+}
+
+/// What measuring one position found
+struct Level {
+    /// the slowest byte
+    byte: u8,
+    /// its score
+    best: f64,
+    /// the median score of all candidates, i.e. roughly that of a wrong byte
+    typical: f64,
+}
+
+/// Finds the byte at `pos` that makes verification slowest, given the bytes before it
+fn measure<V: MacVerifier + ?Sized>(
+    verifier: &V,
+    msg: &[u8],
+    mac: &mut [u8],
+    pos: usize,
+    options: &TimingOptions,
+) -> Level {
+    let mut timings = vec![Vec::new(); 256];
+    let mut scores = vec![0.0; 256];
+    for _ in 0..options.max_rounds.max(1) {
+        // going round all the candidates in turn spreads any drift over all of them
+        for _ in 0..options.samples.max(1) {
+            for (b, samples) in timings.iter_mut().enumerate() {
+                mac[pos] = b as u8;
+                samples.push(time(verifier, msg, mac));
+            }
+        }
+        for (score, samples) in scores.iter_mut().zip(timings.iter()) {
+            *score = options.statistic.apply(samples);
+        }
+        let mut sorted = scores.clone();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        let (best, runner_up, typical) = (sorted[0], sorted[1], sorted[128]);
+        // the right byte should beat the rest by more than the rest differ among themselves
+        if best - runner_up > runner_up - typical {
+            break;
+        }
+    }
+    let mut sorted = scores.clone();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let byte = (0..=255u8)
+        .max_by(|&a, &b| scores[a as usize].total_cmp(&scores[b as usize]))
+        .unwrap();
+    Level {
+        byte,
+        best: sorted[0],
+        typical: sorted[128],
+    }
+}
+
+/// Finds the `mac_len` byte MAC that `verifier` accepts for `msg`, assuming it rejects wrong
+/// MACs faster the earlier they go wrong. The last byte changes nothing about the timing, so
+/// it is found by asking the verifier outright.
+pub fn recover_mac<V: MacVerifier + ?Sized>(
+    verifier: &V,
+    msg: &[u8],
+    mac_len: usize,
+    options: &TimingOptions,
+) -> Result<Vec<u8>, AttackError> {
+    if mac_len == 0 {
+        return Err(AttackError::EmptyMac);
+    }
+    let mut mac = vec![0u8; mac_len];
+    // one per byte guessed so far
+    let mut levels: Vec<Level> = Vec::new();
+    let mut backtracks = 0;
+    loop {
+        let pos = levels.len();
+        if pos == mac_len - 1 {
+            for b in 0..=255 {
+                mac[pos] = b;
+                if verifier.verify(msg, &mac) {
+                    return Ok(mac);
+                }
+            }
+        } else {
+            let level = measure(verifier, msg, &mut mac, pos, options);
+            // after a right guess every candidate here is about as slow as that guess was
+            let consistent = match levels.last() {
+                None => true,
+                Some(prev) => level.typical > (prev.typical + prev.best) / 2.0,
+            };
+            if consistent {
+                mac[pos] = level.byte;
+                levels.push(level);
+                continue;
+            }
+        }
+        // the last guess was wrong
+        if levels.pop().is_none() || backtracks == options.max_backtracks {
+            return Err(AttackError::NotFound);
+        }
+        backtracks += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha1::Sha1;
+    use crate::hash::sha256::Sha256;
+    use crate::mac::insecure_compare;
+    use crate::oracle::{Hardening, HmacOracle};
+    use std::time::Duration;
+
+    #[test]
+    fn test_statistics() {
+        let samples = [5.0, 1.0, 100.0, 3.0, 2.0];
+        assert_eq!(Statistic::Median.apply(&samples), 3.0);
+        assert_eq!(Statistic::Median.apply(&samples[..4]), 4.0);
+        assert_eq!(Statistic::TrimmedMean(0.2).apply(&samples), 10.0 / 3.0);
+        assert_eq!(Statistic::TrimmedMean(0.0).apply(&samples), 22.2);
+        // trimming everything still leaves the middle
+        assert_eq!(Statistic::TrimmedMean(0.5).apply(&samples), 3.0);
+        assert_eq!(Statistic::Median.apply(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_recover_short_mac() {
+        let secret = [0x13, 0x37, 0xbe, 0xef];
+        let delay = Duration::from_micros(20);
+        let verifier = |_: &[u8], mac: &[u8]| insecure_compare(mac, &secret, delay);
+        for &statistic in [Statistic::Median, Statistic::TrimmedMean(0.25)].iter() {
+            let options = TimingOptions {
+                statistic,
+                ..TimingOptions::default()
+            };
+            assert_eq!(recover_mac(&verifier, b"", 4, &options).unwrap(), secret);
+        }
+        assert_eq!(
+            recover_mac(&verifier, b"", 0, &TimingOptions::default()),
+            Err(AttackError::EmptyMac)
+        );
+    }
+
+    #[test]
+    fn test_no_leak() {
+        // without a timing difference the first bytes are a guess, and almost surely wrong
+        let verifier = |_: &[u8], mac: &[u8]| mac == [0x13, 0x37, 0xbe, 0xef];
+        let options = TimingOptions {
+            samples: 1,
+            max_rounds: 1,
+            max_backtracks: 5,
+            ..TimingOptions::default()
+        };
+        assert_eq!(
+            recover_mac(&verifier, b"", 4, &options),
+            Err(AttackError::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_hardened_oracle() {
+        let mut oracle = HmacOracle::<Sha1>::with_seed(3, Duration::from_micros(20));
+        oracle.set_hardening(Hardening::Hardened);
+        let options = TimingOptions {
+            samples: 1,
+            max_rounds: 1,
+            max_backtracks: 5,
+            ..TimingOptions::default()
+        };
+        assert_eq!(
+            recover_mac(&oracle, b"foo", 20, &options),
+            Err(AttackError::NotFound)
+        );
+        assert!(oracle.verify(b"foo", &oracle.sign(b"foo")));
+    }
+
+    #[test]
+    fn test_recover_hmac() {
+        // a leak well under a millisecond per byte is plenty
+        let delay = Duration::from_micros(10);
+        let oracle = HmacOracle::<Sha1>::with_seed(1, delay);
+        let msg = b"foo";
+        let mac = recover_mac(&oracle, msg, 20, &TimingOptions::default()).unwrap();
+        assert_eq!(mac, oracle.sign(msg));
+        assert!(oracle.verify(msg, &mac));
+
+        let oracle = HmacOracle::<Sha256>::with_seed(2, delay);
+        let options = TimingOptions {
+            statistic: Statistic::TrimmedMean(0.2),
+            ..TimingOptions::default()
+        };
+        // only the first few bytes, to keep the test quick
+        let expected = oracle.sign(msg);
+        let verifier = |msg: &[u8], mac: &[u8]| oracle.verify(msg, &[mac, &expected[4..]].concat());
+        assert_eq!(
+            recover_mac(&verifier, msg, 4, &options).unwrap(),
+            &expected[..4]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Hand-rolled argument parsing, to keep the dependency list short
+
+use crate::CliError;
+use cryptopals::encoding::{self, Encoding};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub fn usage<T>(message: String) -> Result<T, CliError> {
+    Err(CliError::Usage(message))
+}
+
+/// An option a command accepts, written `--name`
+pub struct Opt {
+    pub name: &'static str,
+    pub takes_value: bool,
+}
+
+pub const fn flag(name: &'static str) -> Opt {
+    Opt {
+        name,
+        takes_value: false,
+    }
+}
+
+pub const fn value(name: &'static str) -> Opt {
+    Opt {
+        name,
+        takes_value: true,
+    }
+}
+
+#[derive(Debug)]
+pub struct Args {
+    positional: Vec<String>,
+    values: HashMap<&'static str, String>,
+    flags: Vec<&'static str>,
+}
+
+impl Args {
+    /// Options may be given as `--name value` or `--name=value`, anywhere on the line. `-` is a
+    /// positional argument (standard input), and everything after `--` is positional.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, spec: &[Opt]) -> Result<Args, CliError> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            values: HashMap::new(),
+            flags: Vec::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positional.extend(args);
+                break;
+            }
+            if arg == "-h" || arg == "--help" {
+                return Err(CliError::Help);
+            }
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None if arg.starts_with('-') && arg != "-" => {
+                    return usage(format!("unknown option: {}", arg))
+                }
+                None => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+            };
+            let (name, inline_value) = match option.find('=') {
+                Some(i) => (&option[..i], Some(option[i + 1..].to_string())),
+                None => (option, None),
+            };
+            let opt = spec
+                .iter()
+                .find(|opt| opt.name == name)
+                .map_or_else(|| usage(format!("unknown option: --{}", name)), Ok)?;
+            if !opt.takes_value {
+                if inline_value.is_some() {
+                    return usage(format!("--{} does not take a value", name));
+                }
+                parsed.flags.push(opt.name);
+                continue;
+            }
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return usage(format!("--{} needs a value", name)),
+            };
+            if parsed.values.insert(opt.name, value).is_some() {
+                return usage(format!("--{} given more than once", name));
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.value(name)
+            .map_or_else(|| usage(format!("--{} is required", name)), Ok)
+    }
+
+    /// Parses the value of `--name`, or returns `default` if it wasn't given
+    pub fn parsed_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, CliError> {
+        match self.value(name) {
+            None => Ok(default),
+            Some(v) => v.parse().map_or_else(
+                |_| usage(format!("invalid value for --{}: {}", name, v)),
+                Ok,
+            ),
+        }
+    }
+
+    /// Picks the value of `--name` out of `choices`, or returns `default` if it wasn't given
+    pub fn choice_or<T: Copy>(
+        &self,
+        name: &str,
+        choices: &[(&str, T)],
+        default: T,
+    ) -> Result<T, CliError> {
+        match self.value(name) {
+            None => Ok(default),
+            Some(v) => choice(name, v, choices),
+        }
+    }
+
+    /// Fails unless there are between `min` and `max` positional arguments
+    pub fn positional(&self, min: usize, max: usize) -> Result<&[String], CliError> {
+        let n = self.positional.len();
+        if n < min {
+            return usage("missing arguments".to_string());
+        }
+        if n > max {
+            return usage(format!("unexpected argument: {}", self.positional[max]));
+        }
+        Ok(&self.positional)
+    }
+}
+
+pub fn choice<T: Copy>(what: &str, v: &str, choices: &[(&str, T)]) -> Result<T, CliError> {
+    match choices.iter().find(|(name, _)| *name == v) {
+        Some(&(_, choice)) => Ok(choice),
+        None => {
+            let names: Vec<&str> = choices.iter().map(|(name, _)| *name).collect();
+            usage(format!(
+                "invalid {}: {} (expected one of {})",
+                what,
+                v,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// A key or IV given on the command line: `hex:<hex>`, `base64:<base64>`, `raw:<text>`, or
+/// anything else taken literally
+pub fn parse_bytes(what: &str, arg: &str) -> Result<Vec<u8>, CliError> {
+    let decoded = if let Some(hex) = arg.strip_prefix("hex:") {
+        hex::decode(hex).ok()
+    } else if let Some(b64) = arg.strip_prefix("base64:") {
+        base64::decode(b64).ok()
+    } else if let Some(raw) = arg.strip_prefix("raw:") {
+        Some(raw.as_bytes().to_vec())
+    } else {
+        Some(arg.as_bytes().to_vec())
+    };
+    decoded.map_or_else(|| usage(format!("could not decode {}: {}", what, arg)), Ok)
+}
+
+/// How to decode the input: a given encoding, or `auto` to guess it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Auto,
+    Fixed(Encoding),
+}
+
+impl Format {
+    pub const RAW: Format = Format::Fixed(Encoding::Raw);
+
+    pub fn choices() -> Vec<(&'static str, Format)> {
+        let mut choices = vec![("auto", Format::Auto)];
+        choices.extend(
+            encodings()
+                .into_iter()
+                .map(|(name, e)| (name, Format::Fixed(e))),
+        );
+        choices
+    }
+
+    /// The encoding to decode `data` with
+    pub fn resolve(self, data: &[u8]) -> Encoding {
+        match self {
+            Format::Auto => encoding::guess(data).encoding,
+            Format::Fixed(encoding) => encoding,
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Result<Vec<u8>, CliError> {
+        Ok(self.resolve(data).decode(data)?)
+    }
+}
+
+/// The output formats
+pub fn encodings() -> Vec<(&'static str, Encoding)> {
+    Encoding::ALL.iter().map(|&e| (e.name(), e)).collect()
+}
+
+/// Like [`Encoding::encode`], but text gets a trailing newline
+pub fn encode(encoding: Encoding, data: &[u8]) -> Vec<u8> {
+    let mut text = encoding.encode(data);
+    if encoding != Encoding::Raw {
+        text.push(b'\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: [Opt; 3] = [value("key"), value("iv"), flag("lines")];
+
+    fn parse(args: &[&str]) -> Result<Args, CliError> {
+        Args::parse(args.iter().map(|s| s.to_string()), &SPEC)
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&["ecb", "--key", "k1", "--lines", "-", "--iv=hex:00"]).unwrap();
+        assert_eq!(args.positional(2, 2).unwrap(), ["ecb", "-"]);
+        assert_eq!(args.value("key"), Some("k1"));
+        assert_eq!(args.value("iv"), Some("hex:00"));
+        assert!(args.flag("lines"));
+
+        let args = parse(&["--", "--key"]).unwrap();
+        assert_eq!(args.positional(0, 1).unwrap(), ["--key"]);
+        assert!(args.value("key").is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in [
+            &["--nope"][..],
+            &["-x"],
+            &["--key"],
+            &["--lines=yes"],
+            &["--key", "a", "--key", "b"],
+        ]
+        .iter()
+        {
+            assert!(matches!(parse(bad), Err(CliError::Usage(_))), "{:?}", bad);
+        }
+        assert!(matches!(parse(&["--help"]), Err(CliError::Help)));
+
+        let args = parse(&["a", "b"]).unwrap();
+        assert!(args.positional(0, 1).is_err());
+        assert!(args.positional(3, 3).is_err());
+        assert!(args.required("key").is_err());
+    }
+
+    #[test]
+    fn test_parsed_values() {
+        let args = parse(&["--key", "12", "--iv", "cbc"]).unwrap();
+        assert_eq!(args.parsed_or("key", 0usize).unwrap(), 12);
+        assert_eq!(args.parsed_or("lines", 7usize).unwrap(), 7);
+        assert!(args.parsed_or::<usize>("iv", 0).is_err());
+        assert_eq!(args.choice_or("iv", &encodings(), Encoding::Raw).ok(), None);
+        assert_eq!(
+            choice("format", "hex", &Format::choices()).ok(),
+            Some(Format::Fixed(Encoding::Hex))
+        );
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("key", "hex:0aff").unwrap(), [0x0a, 0xff]);
+        assert_eq!(parse_bytes("key", "base64:SUNF").unwrap(), b"ICE");
+        assert_eq!(parse_bytes("key", "raw:hex:00").unwrap(), b"hex:00");
+        assert_eq!(
+            parse_bytes("key", "YELLOW SUBMARINE").unwrap(),
+            b"YELLOW SUBMARINE"
+        );
+        assert!(parse_bytes("key", "hex:xyz").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let hex = Format::Fixed(Encoding::Hex);
+        assert_eq!(hex.decode(b"49 27\n6d\n").unwrap(), b"I'm");
+        assert!(hex.decode(b"4").is_err());
+        assert_eq!(Format::RAW.decode(b"49276d\n").unwrap(), b"49276d\n");
+        assert_eq!(Format::Auto.decode(b"49276d\n").unwrap(), b"I'm");
+        assert_eq!(Format::Auto.decode(b"SSdt\r\n").unwrap(), b"I'm");
+        assert_eq!(Format::Auto.decode(b"I'm\n").unwrap(), b"I'm\n");
+        assert_eq!(encode(Encoding::Base64, b"I'm"), b"SSdt\n");
+        assert_eq!(encode(Encoding::Raw, b"I'm"), b"I'm");
+    }
+}
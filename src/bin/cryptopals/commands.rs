@@ -0,0 +1,460 @@
+use crate::args::{
+    choice, encode as encode_output, encodings, flag, parse_bytes, usage, value, Args, Format, Opt,
+};
+use crate::CliError;
+use aes::cipher::generic_array::GenericArray;
+use aes::{Aes128, Aes192, Aes256, BlockDecrypt, BlockEncrypt, NewBlockCipher};
+use cryptopals::cos_sim::CharFreq;
+use cryptopals::data;
+use cryptopals::encoding::{self, Encoding};
+use cryptopals::io_utils::XorReader;
+use cryptopals::modes::ctr::{CounterLayout, Ctr};
+use cryptopals::modes::{cbc, ctr, ecb};
+use cryptopals::ngram::{normalize, NgramModel, Smoothing};
+use cryptopals::padding::Padding;
+use cryptopals::repeating_xor::{break_repeating_key_xor, BreakOptions, KeySizeMetric};
+use cryptopals::scoring::{ChiSquared, PrintableRatio, Scorer};
+use cryptopals::{brute_force_single_byte_xor, count_repeated_blocks, try_repeating_key_xor};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// Used by the cosine and chi-squared scorers unless --freqs is given
+const DEFAULT_FREQS: &str = include_str!("../../../good_charfreqs.csv");
+// add-k with k < 1 leaves more probability mass for the n-grams we actually saw; Laplace
+// smoothing flattens trigram tables too much
+const SMOOTHING: Smoothing = Smoothing::AddK(0.5);
+
+const IN_FORMAT: Opt = value("in-format");
+const OUT_FORMAT: Opt = value("out-format");
+const OUTPUT: Opt = value("output");
+
+fn open_input(path: Option<&String>) -> io::Result<Box<dyn Read>> {
+    Ok(match path.map(String::as_str) {
+        None | Some("-") => Box::new(io::stdin()),
+        Some(path) => Box::new(File::open(path)?),
+    })
+}
+
+fn read_input(path: Option<&String>) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open_input(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn open_output(args: &Args) -> io::Result<Box<dyn Write>> {
+    Ok(match args.value("output") {
+        None | Some("-") => Box::new(io::stdout()),
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+    })
+}
+
+fn write_output(args: &Args, data: &[u8]) -> Result<(), CliError> {
+    let format = args.choice_or("out-format", &encodings(), Encoding::Raw)?;
+    let mut out = open_output(args)?;
+    out.write_all(&encode_output(format, data))?;
+    out.flush()?;
+    Ok(())
+}
+
+fn read_decoded(args: &Args, path: Option<&String>) -> Result<Vec<u8>, CliError> {
+    let format = args.choice_or("in-format", &Format::choices(), Format::RAW)?;
+    format.decode(&read_input(path)?)
+}
+
+/// Non-empty lines of the input with their 1-based line numbers, each decoded separately. With
+/// `auto`, the encoding is guessed once for the whole input.
+fn read_lines(
+    args: &Args,
+    path: Option<&String>,
+    default: Format,
+) -> Result<Vec<(usize, Vec<u8>)>, CliError> {
+    let format = args.choice_or("in-format", &Format::choices(), default)?;
+    let data = read_input(path)?;
+    let mut lines = Vec::new();
+    for record in data::records(&data[..], format.resolve(&data)) {
+        let record = record?;
+        lines.push((record.line, record.data));
+    }
+    Ok(lines)
+}
+
+pub fn encode<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(argv, &[value("from"), value("to"), OUTPUT])?;
+    let input = args.positional(0, 1)?;
+    let from = args.choice_or("from", &Format::choices(), Format::RAW)?;
+    let to = choice("format", args.required("to")?, &encodings())?;
+
+    let data = from.decode(&read_input(input.first())?)?;
+    let mut out = open_output(&args)?;
+    out.write_all(&encode_output(to, &data))?;
+    out.flush()?;
+    Ok(())
+}
+
+pub fn xor<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(argv, &[value("key"), IN_FORMAT, OUT_FORMAT, OUTPUT])?;
+    let input = args.positional(0, 1)?;
+    let key = parse_bytes("key", args.required("key")?)?;
+    if key.is_empty() {
+        return usage("key is empty".to_string());
+    }
+
+    let in_format = args.choice_or("in-format", &Format::choices(), Format::RAW)?;
+    let out_format = args.choice_or("out-format", &encodings(), Encoding::Raw)?;
+    if in_format == Format::RAW && out_format == Encoding::Raw {
+        // no need to hold the whole input in memory
+        let mut reader = XorReader::repeating(open_input(input.first())?, &key);
+        let mut out = open_output(&args)?;
+        io::copy(&mut reader, &mut out)?;
+        out.flush()?;
+        return Ok(());
+    }
+    let data = in_format.decode(&read_input(input.first())?)?;
+    write_output(&args, &try_repeating_key_xor(&data, &key)?)
+}
+
+fn scorer(args: &Args) -> Result<Box<dyn Scorer>, CliError> {
+    let name = args.value("scorer").unwrap_or("cosine");
+    if name == "ngram" {
+        let path = args.required("model")?;
+        let model = NgramModel::read_from(BufReader::new(File::open(path)?))?;
+        return Ok(Box::new(model));
+    }
+    let freqs = match args.value("freqs") {
+        Some(path) => CharFreq::from_csv(BufReader::new(File::open(path)?))?,
+        None => CharFreq::from_csv(DEFAULT_FREQS.as_bytes())?,
+    };
+    Ok(match name {
+        "cosine" => Box::new(freqs),
+        "chi-squared" => Box::new(ChiSquared(freqs)),
+        "printable" => Box::new(PrintableRatio),
+        other => {
+            return usage(format!(
+                "invalid scorer: {} (expected one of cosine, chi-squared, printable, ngram)",
+                other
+            ))
+        }
+    })
+}
+
+struct Candidate {
+    line: Option<usize>,
+    key: Vec<u8>,
+    score: f64,
+    plaintext: Vec<u8>,
+}
+
+pub fn break_xor<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            flag("single-byte"),
+            flag("lines"),
+            value("top"),
+            value("scorer"),
+            value("freqs"),
+            value("model"),
+            value("metric"),
+            value("min-key-len"),
+            value("max-key-len"),
+            value("candidates"),
+            IN_FORMAT,
+            OUTPUT,
+        ],
+    )?;
+    let input = args.positional(0, 1)?;
+    let scorer = scorer(&args)?;
+    let defaults = BreakOptions::default();
+    let options = BreakOptions {
+        min_key_len: args.parsed_or("min-key-len", defaults.min_key_len)?,
+        max_key_len: args.parsed_or("max-key-len", defaults.max_key_len)?,
+        candidates: args.parsed_or("candidates", defaults.candidates)?,
+        metric: args.choice_or(
+            "metric",
+            &[
+                ("hamming", KeySizeMetric::Hamming),
+                ("ioc", KeySizeMetric::IndexOfCoincidence),
+                ("friedman", KeySizeMetric::Friedman),
+                ("kasiski", KeySizeMetric::Kasiski),
+                ("kasiski-divisibility", KeySizeMetric::KasiskiDivisibility),
+                ("autocorrelation", KeySizeMetric::Autocorrelation),
+            ],
+            defaults.metric,
+        )?,
+    };
+    let top: usize = args.parsed_or("top", 0)?;
+
+    let ciphertexts = if args.flag("lines") {
+        read_lines(&args, input.first(), Format::RAW)?
+            .into_iter()
+            .map(|(no, ct)| (Some(no), ct))
+            .collect()
+    } else {
+        vec![(None, read_decoded(&args, input.first())?)]
+    };
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for (line, ciphertext) in ciphertexts {
+        if args.flag("single-byte") {
+            candidates.extend(
+                brute_force_single_byte_xor(&ciphertext, scorer.as_ref())
+                    .into_iter()
+                    .map(|r| Candidate {
+                        line,
+                        key: vec![r.key],
+                        score: r.score,
+                        plaintext: r.plaintext,
+                    }),
+            );
+        } else {
+            candidates.extend(
+                break_repeating_key_xor(&ciphertext, scorer.as_ref(), &options)
+                    .into_iter()
+                    .map(|s| Candidate {
+                        line,
+                        key: s.key,
+                        score: s.score,
+                        plaintext: s.plaintext,
+                    }),
+            );
+        }
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    if top > 0 {
+        let mut out = open_output(&args)?;
+        for c in candidates.iter().take(top) {
+            if let Some(line) = c.line {
+                write!(out, "line {} ", line)?;
+            }
+            writeln!(
+                out,
+                "key {} ({:.4}) {}",
+                hex::encode(&c.key),
+                c.score,
+                String::from_utf8_lossy(&c.plaintext).escape_default()
+            )?;
+        }
+        out.flush()?;
+        return Ok(());
+    }
+
+    let best = match candidates.first() {
+        Some(best) => best,
+        None => return Err(CliError::Failed("ciphertext is too short".into())),
+    };
+    if let Some(line) = best.line {
+        eprintln!("line: {}", line);
+    }
+    eprintln!(
+        "key: {} ({})",
+        hex::encode(&best.key),
+        String::from_utf8_lossy(&best.key).escape_default()
+    );
+    let mut out = open_output(&args)?;
+    out.write_all(&best.plaintext)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AesMode {
+    Ecb,
+    Cbc,
+    Ctr,
+}
+
+struct AesJob {
+    mode: AesMode,
+    decrypt: bool,
+    iv: Option<Vec<u8>>,
+    nonce: Option<Vec<u8>>,
+    padding: Padding,
+}
+
+impl AesJob {
+    fn run<C: BlockEncrypt + BlockDecrypt + NewBlockCipher>(
+        &self,
+        key: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, CliError> {
+        let cipher = C::new(GenericArray::from_slice(key));
+        let iv = self.iv.as_deref();
+        Ok(match (self.mode, self.decrypt) {
+            (AesMode::Ecb, false) => ecb::encrypt_padded(&cipher, data, self.padding)?,
+            (AesMode::Ecb, true) => ecb::decrypt_padded(&cipher, data, self.padding)?,
+            (AesMode::Cbc, false) => {
+                cbc::try_encrypt_padded(&cipher, iv.unwrap_or(&[]), data, self.padding)?
+            }
+            (AesMode::Cbc, true) => {
+                cbc::try_decrypt_padded(&cipher, iv.unwrap_or(&[]), data, self.padding)?
+            }
+            (AesMode::Ctr, _) => match (iv, &self.nonce) {
+                (Some(iv), _) => ctr::try_encrypt(&cipher, iv, data)?,
+                (None, Some(nonce)) => {
+                    let mut result = data.to_vec();
+                    Ctr::try_with_nonce(&cipher, nonce, CounterLayout::CRYPTOPALS)?
+                        .apply_keystream(&mut result);
+                    result
+                }
+                (None, None) => unreachable!("checked when parsing"),
+            },
+        })
+    }
+}
+
+pub fn aes<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            value("key"),
+            value("iv"),
+            value("nonce"),
+            value("padding"),
+            IN_FORMAT,
+            OUT_FORMAT,
+            OUTPUT,
+        ],
+    )?;
+    let positional = args.positional(2, 3)?;
+    let mode = choice(
+        "mode",
+        &positional[0],
+        &[
+            ("ecb", AesMode::Ecb),
+            ("cbc", AesMode::Cbc),
+            ("ctr", AesMode::Ctr),
+        ],
+    )?;
+    let decrypt = choice(
+        "operation",
+        &positional[1],
+        &[("encrypt", false), ("decrypt", true)],
+    )?;
+    let key = parse_bytes("key", args.required("key")?)?;
+    let iv = args.value("iv").map(|v| parse_bytes("IV", v)).transpose()?;
+    let nonce = args
+        .value("nonce")
+        .map(|v| parse_bytes("nonce", v))
+        .transpose()?;
+    match (mode, &iv, &nonce) {
+        (AesMode::Ecb, None, None) => {}
+        (AesMode::Ecb, _, _) => return usage("ECB takes no IV or nonce".to_string()),
+        (AesMode::Cbc, Some(_), None) => {}
+        (AesMode::Cbc, _, _) => return usage("CBC needs --iv, and no nonce".to_string()),
+        (AesMode::Ctr, Some(_), None) | (AesMode::Ctr, None, Some(_)) => {}
+        (AesMode::Ctr, _, _) => return usage("CTR needs one of --iv or --nonce".to_string()),
+    }
+    if mode == AesMode::Ctr && args.value("padding").is_some() {
+        return usage("CTR takes no padding".to_string());
+    }
+    let job = AesJob {
+        mode,
+        decrypt,
+        iv,
+        nonce,
+        padding: args.choice_or(
+            "padding",
+            &[
+                ("pkcs7", Padding::Pkcs7),
+                ("ansi-x923", Padding::AnsiX923),
+                ("iso7816", Padding::Iso7816),
+                ("zero", Padding::Zero),
+                ("none", Padding::None),
+            ],
+            Padding::Pkcs7,
+        )?,
+    };
+
+    let data = read_decoded(&args, positional.get(2))?;
+    let result = match key.len() {
+        16 => job.run::<Aes128>(&key, &data)?,
+        24 => job.run::<Aes192>(&key, &data)?,
+        32 => job.run::<Aes256>(&key, &data)?,
+        n => return usage(format!("AES keys are 16, 24 or 32 bytes, not {}", n)),
+    };
+    write_output(&args, &result)
+}
+
+pub fn detect_ecb<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(argv, &[value("block-len"), IN_FORMAT, OUTPUT])?;
+    let input = args.positional(0, 1)?;
+    let block_len: usize = args.parsed_or("block-len", 16)?;
+    if block_len == 0 {
+        return usage("block length must be positive".to_string());
+    }
+
+    // ECB encrypts identical plaintext blocks to identical ciphertext blocks, so the ECB line is
+    // the one that repeats itself. Random noise essentially never does.
+    let mut scores: Vec<(usize, usize)> =
+        read_lines(&args, input.first(), Format::Fixed(Encoding::Hex))?
+            .into_iter()
+            .map(|(no, ciphertext)| (no, count_repeated_blocks(&ciphertext, block_len)))
+            .filter(|&(_, repeats)| repeats > 0)
+            .collect();
+    scores.sort_by_key(|&(_, repeats)| std::cmp::Reverse(repeats));
+
+    let mut out = open_output(&args)?;
+    for (no, repeats) in scores.iter() {
+        writeln!(out, "line {} has {} repeated blocks", no, repeats)?;
+    }
+    out.flush()?;
+    if scores.is_empty() {
+        eprintln!("no line repeats a block");
+    }
+    Ok(())
+}
+
+pub fn detect_encoding<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(argv, &[OUTPUT])?;
+    let input = args.positional(0, 1)?;
+    let data = read_input(input.first())?;
+
+    let mut out = open_output(&args)?;
+    for guess in encoding::detect(&data) {
+        writeln!(out, "{:<12} {:.4}", guess.encoding, guess.confidence)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn charfreq<I: Iterator<Item = String>>(argv: I) -> Result<(), CliError> {
+    let args = Args::parse(argv, &[value("csv"), value("bigram"), value("trigram")])?;
+    let positional = args.positional(2, 2)?;
+    if positional[0] != "train" {
+        return usage(format!("unknown charfreq command: {}", positional[0]));
+    }
+    let csv = args.required("csv")?;
+
+    let text = normalize(&fs::read_to_string(&positional[1])?);
+    let mut counts: HashMap<u8, usize> = HashMap::new();
+    for &chr in text.iter() {
+        *counts.entry(chr).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(u8, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|x| x.0);
+
+    let mut out_file = BufWriter::new(File::create(csv)?);
+    for (chr, cnt) in counts {
+        let chr = chr as char;
+        println!(
+            "{}\t({}) {}",
+            chr.escape_default(),
+            chr.escape_unicode(),
+            cnt
+        );
+        writeln!(out_file, "{},{}", chr as u8, cnt)?;
+    }
+    out_file.flush()?;
+
+    for &(n, name) in [(2, "bigram"), (3, "trigram")].iter() {
+        if let Some(path) = args.value(name) {
+            let model = NgramModel::train(&text, n, SMOOTHING);
+            let mut writer = BufWriter::new(File::create(path)?);
+            model.write_to(&mut writer)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Command line front end for the library. Run with `--help` for usage.
+
+mod args;
+mod commands;
+
+use std::env;
+use std::error;
+use std::process;
+
+const USAGE: &str = "\
+usage: cryptopals <command> [options] [INPUT]
+
+INPUT is a file, or standard input if it is missing or `-`. Output goes to
+standard output unless --output is given.
+
+commands:
+  encode --to FORMAT [--from FORMAT]
+      convert between encodings
+  xor --key KEY
+      XOR with a repeating key
+  break-xor [--single-byte] [--lines] [--top N]
+            [--scorer cosine|chi-squared|printable|ngram] [--freqs CSV] [--model FILE]
+            [--metric hamming|ioc|friedman|kasiski|kasiski-divisibility|autocorrelation]
+            [--min-key-len N] [--max-key-len N] [--candidates N]
+      recover the key and plaintext of repeating-key (or single-byte) XOR.
+      With --lines, every line is a separate ciphertext and the most likely
+      one wins. The key is reported on standard error.
+  aes ecb|cbc|ctr encrypt|decrypt --key KEY [--iv IV | --nonce NONCE]
+            [--padding pkcs7|ansi-x923|iso7816|zero|none]
+      AES-128, 192 or 256 depending on the key length. CBC needs --iv. CTR
+      takes either a full initial counter block (--iv, big-endian counter)
+      or an 8 byte nonce (--nonce, little-endian block counter).
+  detect-ecb [--block-len N]
+      list the lines that repeat a block, most repeats first
+  detect-encoding
+      guess how the input is encoded, most likely first, with the confidence
+      of each guess
+  charfreq train CORPUS --csv FILE [--bigram FILE] [--trigram FILE]
+      count character frequencies, and optionally train n-gram models
+
+common options:
+  --in-format, --out-format FORMAT
+      raw, hex, base64, base64url, base64-nopad, base64-mime or base32.
+      Input may also be auto, to guess. Defaults are raw, except hex for
+      detect-ecb input. Whitespace in text formats is ignored.
+  --output FILE
+
+KEY, IV and NONCE are taken literally unless prefixed with hex:, base64: or raw:.
+";
+
+#[derive(Debug)]
+pub enum CliError {
+    Help,
+    Usage(String),
+    Failed(Box<dyn error::Error>),
+}
+
+impl<E: error::Error + 'static> From<E> for CliError {
+    fn from(e: E) -> Self {
+        CliError::Failed(Box::new(e))
+    }
+}
+
+fn main() {
+    let mut argv = env::args().skip(1);
+    let command = argv.next();
+    let result = match command.as_deref() {
+        Some("encode") => commands::encode(argv),
+        Some("xor") => commands::xor(argv),
+        Some("break-xor") => commands::break_xor(argv),
+        Some("aes") => commands::aes(argv),
+        Some("detect-ecb") => commands::detect_ecb(argv),
+        Some("detect-encoding") => commands::detect_encoding(argv),
+        Some("charfreq") => commands::charfreq(argv),
+        Some("-h") | Some("--help") => Err(CliError::Help),
+        Some(other) => Err(CliError::Usage(format!("unknown command: {}", other))),
+        None => Err(CliError::Usage("no command given".to_string())),
+    };
+    match result {
+        Ok(()) => {}
+        Err(CliError::Help) => print!("{}", USAGE),
+        Err(CliError::Usage(message)) => {
+            eprint!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(CliError::Failed(e)) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -35,11 +35,41 @@ pub struct CharFreq {
     magnitude: f64,
 }
 
+/// Where `b` is counted among the 70 characters of the alphabet, if it is in it
+fn bucket(b: u8) -> Option<usize> {
+    match b {
+        // tab, lf
+        9..=10 => Some(b as usize - 9),
+        // most chars
+        32..=96 => Some(b as usize - 30),
+        // { | } ~
+        123..=126 => Some(b as usize - 57),
+        _ => None,
+    }
+}
+
 impl CharFreq {
+    /// Frequencies as fractions of `len`, which may be more than the counts add up to
+    fn from_counts(counts: &[u32; 70], len: f64) -> CharFreq {
+        let mut frequencies: [f64; 70] = [0.0; 70];
+        if len > 0.0 {
+            for (freq, cnt) in frequencies.iter_mut().zip(counts.iter()) {
+                *freq = (*cnt as f64) / len;
+            }
+        }
+
+        let magnitude = dot_product(&frequencies, &frequencies).sqrt();
+
+        CharFreq {
+            frequencies,
+            magnitude,
+        }
+    }
+
     pub fn from_csv<R: io::BufRead>(r: R) -> Result<CharFreq, CharFreqError> {
         let mut counts: [u32; 70] = [0; 70];
         for line in r.lines() {
-            let line = line.map_err(|e| CharFreqError::IoError(e))?;
+            let line = line.map_err(CharFreqError::IoError)?;
             let mut parts = line.split(',');
             let chr = parts
                 .next()
@@ -56,66 +86,59 @@ impl CharFreq {
             let cnt: u32 = cnt
                 .parse()
                 .map_err(|_| CharFreqError::InvalidCsv("invalid count"))?;
-            match chr {
-                // tab, lf
-                9..=10 => counts[chr as usize - 9] += cnt,
-                // most chars
-                32..=96 => counts[chr as usize - 30] += cnt,
-                // { | } ~
-                123..=126 => counts[chr as usize - 57] += cnt,
-                // all others are invalid
-                _ => return Err(CharFreqError::InvalidChar(chr)),
-            }
+            counts[bucket(chr).ok_or(CharFreqError::InvalidChar(chr))?] += cnt;
         }
 
         let len = counts.iter().sum::<u32>() as f64;
-
-        let mut frequencies: [f64; 70] = [0.0; 70];
-        for (i, cnt) in counts.iter().enumerate() {
-            frequencies[i] = (*cnt as f64) / len;
-        }
-
-        let magnitude = dot_product(&frequencies, &frequencies);
-
-        Ok(CharFreq {
-            frequencies,
-            magnitude,
-        })
+        Ok(CharFreq::from_counts(&counts, len))
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<CharFreq, CharFreqError> {
         let mut counts: [u32; 70] = [0; 70];
-        let uppercase = s.to_ascii_uppercase().into_bytes();
-        let len = uppercase.len() as f64;
-        for b in uppercase {
-            match b {
-                // tab, lf
-                9..=10 => counts[b as usize - 9] += 1,
-                // most chars
-                32..=96 => counts[b as usize - 30] += 1,
-                // { | } ~
-                123..=126 => counts[b as usize - 57] += 1,
-                // all others are invalid
-                _ => return Err(CharFreqError::InvalidChar(b)),
-            }
+        for b in s.bytes().map(|b| b.to_ascii_uppercase()) {
+            counts[bucket(b).ok_or(CharFreqError::InvalidChar(b))?] += 1;
         }
+        Ok(CharFreq::from_counts(&counts, s.len() as f64))
+    }
 
-        let mut frequencies: [f64; 70] = [0.0; 70];
-        for (i, cnt) in counts.iter().enumerate() {
-            frequencies[i] = (*cnt as f64) / len;
+    /// Like `from_str`, but bytes outside the alphabet are counted instead of rejected. The
+    /// frequencies are fractions of all of `bytes`, so they sum to less than 1 when some bytes
+    /// were outside the alphabet. Also returns the number of those bytes.
+    pub fn from_bytes(bytes: &[u8]) -> (CharFreq, usize) {
+        let mut counts: [u32; 70] = [0; 70];
+        let mut out_of_alphabet = 0;
+        for b in bytes.iter().map(u8::to_ascii_uppercase) {
+            match bucket(b) {
+                Some(i) => counts[i] += 1,
+                None => out_of_alphabet += 1,
+            }
         }
-
-        let magnitude = dot_product(&frequencies, &frequencies);
-
-        Ok(CharFreq {
-            frequencies,
-            magnitude,
-        })
+        (
+            CharFreq::from_counts(&counts, bytes.len() as f64),
+            out_of_alphabet,
+        )
     }
 
-    /// Higher number means more similar
+    /// Higher number means more similar, 0-1 inclusive
     pub fn cosine_similarity(&self, other: &CharFreq) -> f64 {
         dot_product(&self.frequencies, &other.frequencies) / (self.magnitude * other.magnitude)
     }
+
+    /// Pearson's chi-squared statistic per observed character, treating `self` as observed and
+    /// `expected` as the reference distribution. Lower number means more similar. Characters
+    /// that never occur in `expected` are counted as if they had a tiny frequency instead of
+    /// making the result infinite.
+    pub fn chi_squared(&self, expected: &CharFreq) -> f64 {
+        const MIN_EXPECTED: f64 = 1e-6;
+        self.frequencies
+            .iter()
+            .zip(expected.frequencies.iter())
+            .map(|(&o, &e)| {
+                let e = e.max(MIN_EXPECTED);
+                (o - e) * (o - e) / e
+            })
+            .sum()
+    }
 }
 
 // todo: this is decent, but the compiler fails to auto-vectorize
@@ -128,3 +151,42 @@ fn dot_product(a: &[f64; 70], b: &[f64; 70]) -> f64 {
         .zip(b.iter())
         .fold(0.0, |a, (&l, &r)| l.mul_add(r, a))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        let a = CharFreq::from_str("A").unwrap();
+        let ab = CharFreq::from_str("AB").unwrap();
+        assert!((a.cosine_similarity(&a) - 1.0).abs() < 1e-12);
+        // (1, 0) against (0.5, 0.5) is 45 degrees apart
+        assert!((a.cosine_similarity(&ab) - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!((ab.cosine_similarity(&a) - 0.5f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_constructors_agree() {
+        let text = "Call me Ishmael.\n{~}";
+        let csv: String = text
+            .to_ascii_uppercase()
+            .bytes()
+            .map(|b| format!("{},1\n", b))
+            .collect();
+        let expected = CharFreq::from_str(text).unwrap().frequencies;
+        assert_eq!(
+            CharFreq::from_csv(csv.as_bytes()).unwrap().frequencies,
+            expected
+        );
+        let (from_bytes, out_of_alphabet) = CharFreq::from_bytes(text.as_bytes());
+        assert_eq!(from_bytes.frequencies, expected);
+        assert_eq!(out_of_alphabet, 0);
+
+        assert!(matches!(
+            CharFreq::from_str("caf\u{e9}"),
+            Err(CharFreqError::InvalidChar(0xc3))
+        ));
+        assert_eq!(CharFreq::from_bytes(b"a\xe9\x00").1, 2);
+    }
+}
@@ -0,0 +1,248 @@
+//! Constant-time helpers. Everything here takes the same path through the code whatever the
+//! secret bytes are: no early exits and no branches on their values, only masks. Lengths are
+//! treated as public.
+//!
+//! The compiler is free to undo this, so results pass through [`black_box`] before being turned
+//! into a `bool`. That is a best effort, not a guarantee.
+
+use crate::{Error, PaddingError};
+use std::hint::black_box;
+
+/// 0xff for `true`, 0 for `false`
+pub fn mask(choice: bool) -> u8 {
+    (choice as u8).wrapping_neg()
+}
+
+/// 0xff if `a == b`, else 0
+pub fn eq_mask(a: u8, b: u8) -> u8 {
+    // only 0 - 1 borrows into the high bits
+    (((a ^ b) as u32).wrapping_sub(1) >> 8) as u8
+}
+
+/// 0xff if `a < b`, else 0. Both must be below 2^31.
+fn lt_mask(a: u32, b: u32) -> u8 {
+    ((a.wrapping_sub(b) >> 31) as u8).wrapping_neg()
+}
+
+/// `a` where `mask` is set, `b` elsewhere. `mask` is normally all ones or all zeros.
+pub fn select_u8(mask: u8, a: u8, b: u8) -> u8 {
+    b ^ (mask & (a ^ b))
+}
+
+/// OR of the differences of every pair, so 0 only if all pairs are equal
+fn diff<'a, I: Iterator<Item = (&'a u8, &'a u8)>>(pairs: I) -> u8 {
+    pairs.fold(0, |acc, (x, y)| acc | (x ^ y))
+}
+
+/// Equality that looks at every byte however early they differ. Inputs of different lengths
+/// are unequal straight away.
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    black_box(diff(a.iter().zip(b.iter()))) == 0
+}
+
+/// `a` if `choice`, else `b`, built without branching on `choice`. Panics if the lengths differ.
+pub fn select(choice: bool, a: &[u8], b: &[u8]) -> Vec<u8> {
+    try_select(choice, a, b).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`select`], but fails instead of panicking
+pub fn try_select(choice: bool, a: &[u8], b: &[u8]) -> Result<Vec<u8>, Error> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            actual: b.len(),
+        });
+    }
+    let mask = black_box(mask(choice));
+    Ok(a.iter()
+        .zip(b.iter())
+        .map(|(&x, &y)| select_u8(mask, x, y))
+        .collect())
+}
+
+/// 0xff if the last block, given back to front, ends in valid PKCS#7 padding of `pad` bytes.
+/// Reads the whole block whatever it holds.
+fn pkcs7_mask<'a, I: Iterator<Item = &'a u8>>(pad: u8, block_len: usize, rev_block: I) -> u8 {
+    let pad = pad as u32;
+    let mut good = !eq_mask(pad as u8, 0) & lt_mask(pad, block_len as u32 + 1);
+    for (i, &b) in rev_block.enumerate() {
+        let in_padding = lt_mask(i as u32, pad);
+        good &= !in_padding | eq_mask(b, pad as u8);
+    }
+    good
+}
+
+/// The number of PKCS#7 padding bytes at the end of `buf`, checked in constant time. Bad
+/// padding of any kind is reported as [`PaddingError::InconsistentPadding`], so the error says
+/// no more than the timing does. An empty or misaligned `buf` fails early, as its length is
+/// public anyway.
+pub fn pkcs7_padding_len(buf: &[u8], block_len: usize) -> Result<usize, PaddingError> {
+    if block_len == 0 || block_len > u8::MAX as usize {
+        return Err(PaddingError::BadBlockLength(block_len));
+    }
+    let pad = *buf.last().ok_or(PaddingError::EmptyInput)?;
+    if !buf.len().is_multiple_of(block_len) {
+        return Err(PaddingError::NotBlockAligned {
+            len: buf.len(),
+            block_len,
+        });
+    }
+    let last_block = &buf[buf.len() - block_len..];
+    if black_box(pkcs7_mask(pad, block_len, last_block.iter().rev())) == 0 {
+        return Err(PaddingError::InconsistentPadding);
+    }
+    Ok(pad as usize)
+}
+
+/// Strips PKCS#7 padding in place like [`crate::pkcs7_unpad`], but in constant time; see
+/// [`pkcs7_padding_len`]. `buf` is left untouched if the padding is invalid.
+pub fn pkcs7_unpad(buf: &mut Vec<u8>, block_len: usize) -> Result<(), PaddingError> {
+    let pad = pkcs7_padding_len(buf, block_len)?;
+    buf.truncate(buf.len() - pad);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(mask(true), 0xff);
+        assert_eq!(mask(false), 0);
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(eq_mask(a, b), mask(a == b));
+                assert_eq!(lt_mask(a as u32, b as u32), mask(a < b));
+                assert_eq!(select_u8(0xff, a, b), a);
+                assert_eq!(select_u8(0, a, b), b);
+            }
+        }
+        assert_eq!(lt_mask(255, 256), 0xff);
+        assert_eq!(lt_mask(256, 256), 0);
+    }
+
+    #[test]
+    fn test_eq() {
+        assert!(eq(b"", b""));
+        assert!(eq(b"YELLOW SUBMARINE", b"YELLOW SUBMARINE"));
+        assert!(!eq(b"YELLOW SUBMARINE", b"YELLOW SUBMARINF"));
+        assert!(!eq(b"YELLOW SUBMARINE", b"yELLOW SUBMARINE"));
+        assert!(!eq(b"YELLOW", b"YELLOW SUBMARINE"));
+        for i in 0..8 {
+            let mut other = [0u8; 8];
+            other[i] = 0x80;
+            assert!(!eq(&[0; 8], &other));
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(select(true, b"abc", b"xyz"), b"abc");
+        assert_eq!(select(false, b"abc", b"xyz"), b"xyz");
+        assert!(matches!(
+            try_select(true, b"abc", b"xy"),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_pkcs7_matches_reference() {
+        // every possible final byte, over blocks that are good padding up to some point
+        for block_len in [1, 8, 16].iter().copied() {
+            for pad in 0..=255u8 {
+                for good_bytes in 0..=block_len {
+                    let mut buf = vec![b'A'; 2 * block_len];
+                    let len = buf.len();
+                    for b in buf[len - good_bytes..].iter_mut() {
+                        *b = pad;
+                    }
+                    *buf.last_mut().unwrap() = pad;
+
+                    let mut expected = buf.clone();
+                    let expected_result = crate::pkcs7_unpad(&mut expected, block_len);
+                    let mut actual = buf.clone();
+                    let result = pkcs7_unpad(&mut actual, block_len);
+                    assert_eq!(result.is_ok(), expected_result.is_ok(), "{:?}", buf);
+                    assert_eq!(actual, expected);
+                    if result.is_err() {
+                        assert_eq!(result, Err(PaddingError::InconsistentPadding));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_shape_errors() {
+        assert_eq!(pkcs7_padding_len(b"", 16), Err(PaddingError::EmptyInput));
+        assert_eq!(
+            pkcs7_padding_len(b"\x01", 16),
+            Err(PaddingError::NotBlockAligned {
+                len: 1,
+                block_len: 16
+            })
+        );
+        assert_eq!(
+            pkcs7_padding_len(b"\x01", 0),
+            Err(PaddingError::BadBlockLength(0))
+        );
+        assert_eq!(pkcs7_padding_len(&[4; 16], 16), Ok(4));
+    }
+
+    #[test]
+    fn test_reads_every_byte() {
+        // a short-circuiting loop would stop at the first difference or bad padding byte
+        let secret = [0x42u8; 32];
+        for i in 0..secret.len() {
+            let mut guess = secret;
+            guess[i] ^= 1;
+            let mut reads = 0;
+            assert_ne!(
+                diff(secret.iter().zip(guess.iter()).inspect(|_| reads += 1)),
+                0
+            );
+            assert_eq!(reads, secret.len());
+        }
+
+        for block in [[0u8; 16], [16; 16], [3; 16], [0xff; 16]].iter() {
+            for pad in [0, 1, 3, 16, 17, 255].iter().copied() {
+                let mut reads = 0;
+                pkcs7_mask(pad, 16, block.iter().rev().inspect(|_| reads += 1));
+                assert_eq!(reads, 16, "{:?} {}", block, pad);
+            }
+        }
+    }
+
+    /// Fastest of several runs, which is the least disturbed by everything else on the machine
+    fn fastest<F: FnMut()>(mut f: F) -> Duration {
+        (0..20)
+            .map(|_| {
+                let start = Instant::now();
+                f();
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_timing_independent_of_data() {
+        let secret = vec![0x42u8; 1 << 16];
+        let mut early = secret.clone();
+        early[0] ^= 1;
+
+        let same = fastest(|| assert!(eq(&secret, &secret)));
+        let differ = fastest(|| assert!(!eq(&secret, &early)));
+        // stopping at the first byte would be thousands of times faster
+        let ratio = same.as_secs_f64() / differ.as_secs_f64();
+        assert!(ratio > 0.5 && ratio < 2.0, "{}", ratio);
+    }
+}
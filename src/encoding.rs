@@ -0,0 +1,474 @@
+//! Text encodings for binary data, and a guesser for which one a file uses.
+//!
+//! Decoding ignores ASCII whitespace everywhere except in [`Encoding::Raw`], so wrapped files like
+//! `s1c6_data.txt` decode as a whole.
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// MIME base64 wraps lines at this many characters
+pub const MIME_LINE_LEN: usize = 76;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// the bytes themselves
+    Raw,
+    /// lowercase when encoding, either case when decoding
+    Hex,
+    /// RFC 4648 base64, padded
+    Base64,
+    /// base64 with `-` and `_` in place of `+` and `/`, padded
+    Base64Url,
+    /// standard base64 without the trailing `=`
+    Base64NoPad,
+    /// standard base64 wrapped at [`MIME_LINE_LEN`] characters with CRLF line breaks
+    Base64Mime,
+    /// RFC 4648 base32, uppercase and padded
+    Base32,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 7] = [
+        Encoding::Raw,
+        Encoding::Hex,
+        Encoding::Base64,
+        Encoding::Base64Url,
+        Encoding::Base64NoPad,
+        Encoding::Base64Mime,
+        Encoding::Base32,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Raw => "raw",
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base64NoPad => "base64-nopad",
+            Encoding::Base64Mime => "base64-mime",
+            Encoding::Base32 => "base32",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        Encoding::ALL.iter().copied().find(|e| e.name() == name)
+    }
+
+    pub fn encode(self, data: &[u8]) -> Vec<u8> {
+        let text = match self {
+            Encoding::Raw => return data.to_vec(),
+            Encoding::Hex => hex::encode(data),
+            Encoding::Base64 => base64::encode_config(data, base64::STANDARD),
+            Encoding::Base64Url => base64::encode_config(data, base64::URL_SAFE),
+            Encoding::Base64NoPad => base64::encode_config(data, base64::STANDARD_NO_PAD),
+            Encoding::Base64Mime => {
+                let text = base64::encode_config(data, base64::STANDARD);
+                let lines: Vec<&[u8]> = text.as_bytes().chunks(MIME_LINE_LEN).collect();
+                return lines.join(&b"\r\n"[..]);
+            }
+            Encoding::Base32 => base32_encode(data),
+        };
+        text.into_bytes()
+    }
+
+    /// Padding is optional in all the base64 flavours except [`Encoding::Base64NoPad`], which
+    /// rejects it.
+    pub fn decode(self, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        if self == Encoding::Raw {
+            return Ok(data.to_vec());
+        }
+        let (text, offsets) = strip_whitespace(data);
+        // errors from the decoders count from the start of the stripped text
+        let at = |i: usize| DecodeError::InvalidByte {
+            offset: offsets[i],
+            byte: text[i],
+        };
+        let config = match self {
+            Encoding::Raw => unreachable!(),
+            Encoding::Hex => {
+                return hex::decode(&text).map_err(|e| match e {
+                    hex::FromHexError::InvalidHexCharacter { index, .. } => at(index),
+                    _ => DecodeError::InvalidLength,
+                })
+            }
+            Encoding::Base32 => {
+                return base32_decode(&text).map_err(|e| e.map_or(DecodeError::InvalidLength, at))
+            }
+            Encoding::Base64 | Encoding::Base64Mime => base64::STANDARD,
+            Encoding::Base64Url => base64::URL_SAFE,
+            Encoding::Base64NoPad => {
+                if let Some(i) = text.iter().position(|&b| b == b'=') {
+                    return Err(at(i));
+                }
+                base64::STANDARD_NO_PAD
+            }
+        };
+        base64::decode_config(&text, config).map_err(|e| match e {
+            base64::DecodeError::InvalidByte(i, _)
+            | base64::DecodeError::InvalidLastSymbol(i, _) => at(i),
+            base64::DecodeError::InvalidLength => DecodeError::InvalidLength,
+        })
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// `offset` counts from the start of the input, whitespace included
+    InvalidByte { offset: usize, byte: u8 },
+    /// the input stops partway through a byte
+    InvalidLength,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidByte { offset, byte } => {
+                write!(f, "invalid byte {:#04x} at offset {}", byte, offset)
+            }
+            DecodeError::InvalidLength => write!(f, "invalid length"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// The input with whitespace removed, and where each remaining byte came from
+fn strip_whitespace(data: &[u8]) -> (Vec<u8>, Vec<usize>) {
+    data.iter()
+        .enumerate()
+        .filter(|(_, b)| !b.is_ascii_whitespace())
+        .map(|(i, &b)| (b, i))
+        .unzip()
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(5) * 8);
+    for chunk in data.chunks(5) {
+        let mut group = [0u8; 8];
+        group[3..3 + chunk.len()].copy_from_slice(chunk);
+        let bits = u64::from_be_bytes(group);
+        // 8 bits per byte, 5 per symbol, rounded up
+        let symbols = (chunk.len() * 8).div_ceil(5);
+        for i in 0..8 {
+            if i < symbols {
+                let index = (bits >> (35 - 5 * i)) & 0x1f;
+                text.push(BASE32_ALPHABET[index as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// On failure, the index of the bad symbol, or `None` if the length is wrong
+fn base32_decode(text: &[u8]) -> Result<Vec<u8>, Option<usize>> {
+    let symbols = text.iter().position(|&b| b == b'=').unwrap_or(text.len());
+    if let Some(i) = text[symbols..].iter().position(|&b| b != b'=') {
+        return Err(Some(symbols + i));
+    }
+    // a partial group of 1 to 4 bytes takes 2, 4, 5 or 7 symbols
+    let padded = symbols != text.len();
+    if !matches!(symbols % 8, 0 | 2 | 4 | 5 | 7) || (padded && !text.len().is_multiple_of(8)) {
+        return Err(None);
+    }
+    let mut data = Vec::with_capacity(symbols * 5 / 8);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for (i, &b) in text[..symbols].iter().enumerate() {
+        let value = match BASE32_ALPHABET.iter().position(|&s| s == b) {
+            Some(value) => value as u32,
+            None => return Err(Some(i)),
+        };
+        acc = (acc << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    // the leftover bits must be zero, or there would be two encodings of the same data
+    if acc != 0 {
+        return Err(Some(symbols - 1));
+    }
+    Ok(data)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Guess {
+    pub encoding: Encoding,
+    /// 0-1 inclusive; the guesses from one call to [`detect`] sum to 1
+    pub confidence: f64,
+}
+
+/// Every encoding `data` decodes under, most likely first. [`Encoding::Raw`] is always included.
+///
+/// Each candidate is weighed by the chance that random data in that encoding would come out as
+/// exactly this text, so a smaller alphabet that explains the input wins: `deadbeef` is far
+/// more likely hex than base64. The base64 flavours all decode to the same bytes unless the
+/// text contains `-`, `_` or no padding, so at most one of them is reported, preferring standard
+/// base64, then MIME if every line but the last is [`MIME_LINE_LEN`] long. Short inputs are
+/// often ambiguous and the confidence says so.
+pub fn detect(data: &[u8]) -> Vec<Guess> {
+    let (text, _) = strip_whitespace(data);
+    let symbols = text.iter().filter(|&&b| b != b'=').count();
+    // log2 of the chance of this exact input under each candidate
+    let mut candidates = vec![(Encoding::Raw, -8.0 * data.len() as f64)];
+    if symbols > 0 {
+        for &(encoding, bits) in [
+            (Encoding::Hex, 4.0),
+            (base64_flavour(data, &text), 6.0),
+            (Encoding::Base32, 5.0),
+        ]
+        .iter()
+        {
+            if encoding.decode(data).is_ok() {
+                candidates.push((encoding, -bits * symbols as f64));
+            }
+        }
+    }
+
+    let best = candidates
+        .iter()
+        .map(|&(_, log_p)| log_p)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let total: f64 = candidates
+        .iter()
+        .map(|&(_, log_p)| (log_p - best).exp2())
+        .sum();
+    let mut guesses: Vec<Guess> = candidates
+        .into_iter()
+        .map(|(encoding, log_p)| Guess {
+            encoding,
+            confidence: (log_p - best).exp2() / total,
+        })
+        .collect();
+    guesses.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    guesses
+}
+
+/// The most likely encoding of `data`
+pub fn guess(data: &[u8]) -> Guess {
+    detect(data)[0]
+}
+
+/// Which base64 flavour `data` would be, if it is base64 at all
+fn base64_flavour(data: &[u8], text: &[u8]) -> Encoding {
+    if text.iter().any(|&b| b == b'-' || b == b'_') {
+        return Encoding::Base64Url;
+    }
+    if !text.contains(&b'=') && !text.len().is_multiple_of(4) {
+        return Encoding::Base64NoPad;
+    }
+    let lines: Vec<&[u8]> = data
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .collect();
+    let wrapped = lines.len() > 1
+        && lines[..lines.len() - 1]
+            .iter()
+            .all(|line| line.len() == MIME_LINE_LEN);
+    if wrapped {
+        Encoding::Base64Mime
+    } else {
+        Encoding::Base64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn read(name: &str) -> Vec<u8> {
+        fs::read(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        for &encoding in Encoding::ALL.iter() {
+            for len in [0, 1, 2, 3, 4, 5, 6, 100, 256].iter() {
+                let encoded = encoding.encode(&data[..*len]);
+                assert_eq!(
+                    encoding.decode(&encoded).unwrap(),
+                    &data[..*len],
+                    "{} {}",
+                    encoding,
+                    len
+                );
+            }
+            assert_eq!(Encoding::from_name(encoding.name()), Some(encoding));
+        }
+        assert_eq!(Encoding::from_name("base85"), None);
+    }
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(Encoding::Hex.encode(b"I'm"), b"49276d");
+        assert_eq!(Encoding::Hex.decode(b"49 27\n6D\n").unwrap(), b"I'm");
+        assert_eq!(Encoding::Base64.encode(b"\xfb\xff"), b"+/8=");
+        assert_eq!(Encoding::Base64Url.encode(b"\xfb\xff"), b"-_8=");
+        assert_eq!(Encoding::Base64NoPad.encode(b"\xfb\xff"), b"+/8");
+        assert_eq!(Encoding::Base64.decode(b"SSdt\r\n").unwrap(), b"I'm");
+        assert_eq!(Encoding::Base64.decode(b"+/8").unwrap(), b"\xfb\xff");
+        assert_eq!(Encoding::Raw.decode(b"I'm\n").unwrap(), b"I'm\n");
+
+        // RFC 4648 test vectors
+        for &(plain, encoded) in [
+            ("", ""),
+            ("f", "MY======"),
+            ("fo", "MZXQ===="),
+            ("foo", "MZXW6==="),
+            ("foob", "MZXW6YQ="),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI======"),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                Encoding::Base32.encode(plain.as_bytes()),
+                encoded.as_bytes()
+            );
+            assert_eq!(
+                Encoding::Base32.decode(encoded.as_bytes()).unwrap(),
+                plain.as_bytes()
+            );
+        }
+        assert_eq!(Encoding::Base32.decode(b"MZXW6\n").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn test_mime() {
+        let data = vec![0u8; 100];
+        let encoded = Encoding::Base64Mime.encode(&data);
+        let lines: Vec<&[u8]> = encoded.split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), MIME_LINE_LEN + 1);
+        assert!(lines[0].ends_with(b"\r"));
+        assert_eq!(lines[1].len(), 136 - MIME_LINE_LEN);
+        assert_eq!(Encoding::Base64Mime.decode(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            Encoding::Hex.decode(b"49\n2x"),
+            Err(DecodeError::InvalidByte {
+                offset: 4,
+                byte: b'x'
+            })
+        );
+        assert_eq!(
+            Encoding::Hex.decode(b"492"),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(
+            Encoding::Base64.decode(b"SS\ndt!"),
+            Err(DecodeError::InvalidByte {
+                offset: 5,
+                byte: b'!'
+            })
+        );
+        assert!(Encoding::Base64.decode(b"-_8=").is_err());
+        assert!(Encoding::Base64Url.decode(b"+/8=").is_err());
+        assert_eq!(
+            Encoding::Base64NoPad.decode(b"+/8="),
+            Err(DecodeError::InvalidByte {
+                offset: 3,
+                byte: b'='
+            })
+        );
+        assert_eq!(
+            Encoding::Base32.decode(b"MZX"),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(
+            Encoding::Base32.decode(b"MZXW6=="),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(
+            Encoding::Base32.decode(b"MZ=XW6=="),
+            Err(DecodeError::InvalidByte {
+                offset: 3,
+                byte: b'X'
+            })
+        );
+        assert_eq!(
+            Encoding::Base32.decode(b"mzxw6==="),
+            Err(DecodeError::InvalidByte {
+                offset: 0,
+                byte: b'm'
+            })
+        );
+        // "MZ" is "f", but "M7" has bits left over
+        assert_eq!(
+            Encoding::Base32.decode(b"M7"),
+            Err(DecodeError::InvalidByte {
+                offset: 1,
+                byte: b'7'
+            })
+        );
+        assert_eq!(
+            DecodeError::InvalidByte {
+                offset: 3,
+                byte: b'!'
+            }
+            .to_string(),
+            "invalid byte 0x21 at offset 3"
+        );
+    }
+
+    #[test]
+    fn test_detect_challenge_files() {
+        for &(name, expected) in [
+            ("s1c4_data.txt", Encoding::Hex),
+            ("s1c6_data.txt", Encoding::Base64),
+            ("s1c7_data.txt", Encoding::Base64),
+            ("s1c8_data.txt", Encoding::Hex),
+            ("s2c10_data.txt", Encoding::Base64),
+            ("MobyDick.txt", Encoding::Raw),
+        ]
+        .iter()
+        {
+            let guess = guess(&read(name));
+            assert_eq!(guess.encoding, expected, "{}", name);
+            assert!(guess.confidence > 0.999, "{}: {}", name, guess.confidence);
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let data: Vec<u8> = (0..100).collect();
+        for &encoding in Encoding::ALL.iter() {
+            let guess = guess(&encoding.encode(&data));
+            assert_eq!(guess.encoding, encoding);
+            assert!(
+                guess.confidence > 0.999,
+                "{}: {}",
+                encoding,
+                guess.confidence
+            );
+        }
+
+        let guesses = detect(b"deadbeef");
+        assert_eq!(guesses[0].encoding, Encoding::Hex);
+        assert_eq!(guesses.len(), 3);
+        let total: f64 = guesses.iter().map(|g| g.confidence).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+
+        assert_eq!(guess(b"Hello, world!\n").encoding, Encoding::Raw);
+        assert_eq!(detect(b"").len(), 1);
+        assert_eq!(guess(b" \n").encoding, Encoding::Raw);
+    }
+}
@@ -0,0 +1,263 @@
+//! Crate-wide error type. Every function that panics on badly shaped input has a `try_` variant
+//! returning this instead, and the module-specific errors convert into it, so callers that must
+//! not crash can use `?` throughout.
+
+use crate::attacks::cbc_bitflip::FlipError;
+use crate::attacks::mt_clone::CloneError;
+use crate::attacks::{byte_at_a_time, length_extension, padding_oracle, timing_leak};
+use crate::cos_sim::CharFreqError;
+use crate::data::DataError;
+use crate::encoding::DecodeError;
+use crate::ngram::NgramError;
+use crate::{HammingError, PaddingError};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    EmptyKey,
+    /// two inputs that must be the same length aren't
+    LengthMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// the IV or counter block isn't one block long
+    BadIvLength {
+        expected: usize,
+        actual: usize,
+    },
+    NotBlockAligned {
+        len: usize,
+        block_len: usize,
+    },
+    /// the CTR counter field is longer than the block, or than 16 bytes
+    BadCounterLength(usize),
+    Padding(PaddingError),
+    Decode(DecodeError),
+    Data(DataError),
+    Hamming(HammingError),
+    CharFreq(CharFreqError),
+    Ngram(NgramError),
+    PaddingOracle(padding_oracle::AttackError),
+    ByteAtATime(byte_at_a_time::AttackError),
+    CbcBitflip(FlipError),
+    LengthExtension(length_extension::AttackError),
+    TimingLeak(timing_leak::AttackError),
+    MtClone(CloneError),
+    IoError(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::EmptyKey => write!(f, "key is empty"),
+            Error::LengthMismatch { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::BadIvLength { expected, actual } => {
+                write!(f, "IV must be {} bytes, got {}", expected, actual)
+            }
+            Error::NotBlockAligned { len, block_len } => write!(
+                f,
+                "length {} is not a multiple of the block length {}",
+                len, block_len
+            ),
+            Error::BadCounterLength(len) => write!(f, "bad counter length: {}", len),
+            Error::Padding(e) => write!(f, "padding error: {}", e),
+            Error::Decode(e) => write!(f, "decoding failed: {}", e),
+            Error::Data(e) => write!(f, "bad data file: {}", e),
+            Error::Hamming(e) => write!(f, "{}", e),
+            Error::CharFreq(e) => write!(f, "{}", e),
+            Error::Ngram(e) => write!(f, "{}", e),
+            Error::PaddingOracle(e) => write!(f, "padding oracle attack failed: {}", e),
+            Error::ByteAtATime(e) => write!(f, "byte-at-a-time attack failed: {}", e),
+            Error::CbcBitflip(e) => write!(f, "bit flipping failed: {}", e),
+            Error::LengthExtension(e) => write!(f, "length extension failed: {}", e),
+            Error::TimingLeak(e) => write!(f, "timing attack failed: {}", e),
+            Error::MtClone(e) => write!(f, "cloning the generator failed: {}", e),
+            Error::IoError(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Padding(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            Error::Data(e) => Some(e),
+            Error::Hamming(e) => Some(e),
+            Error::CharFreq(e) => Some(e),
+            Error::Ngram(e) => Some(e),
+            Error::PaddingOracle(e) => Some(e),
+            Error::ByteAtATime(e) => Some(e),
+            Error::CbcBitflip(e) => Some(e),
+            Error::LengthExtension(e) => Some(e),
+            Error::TimingLeak(e) => Some(e),
+            Error::MtClone(e) => Some(e),
+            Error::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Misalignment and bad IVs become the matching [`Error`] variants, so there is only one way to
+/// report each
+impl From<PaddingError> for Error {
+    fn from(e: PaddingError) -> Self {
+        match e {
+            PaddingError::NotBlockAligned { len, block_len } => {
+                Error::NotBlockAligned { len, block_len }
+            }
+            PaddingError::BadIvLength { expected, actual } => {
+                Error::BadIvLength { expected, actual }
+            }
+            e => Error::Padding(e),
+        }
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+impl From<DataError> for Error {
+    fn from(e: DataError) -> Self {
+        Error::Data(e)
+    }
+}
+
+impl From<HammingError> for Error {
+    fn from(e: HammingError) -> Self {
+        Error::Hamming(e)
+    }
+}
+
+impl From<CharFreqError> for Error {
+    fn from(e: CharFreqError) -> Self {
+        Error::CharFreq(e)
+    }
+}
+
+impl From<NgramError> for Error {
+    fn from(e: NgramError) -> Self {
+        Error::Ngram(e)
+    }
+}
+
+impl From<padding_oracle::AttackError> for Error {
+    fn from(e: padding_oracle::AttackError) -> Self {
+        Error::PaddingOracle(e)
+    }
+}
+
+impl From<byte_at_a_time::AttackError> for Error {
+    fn from(e: byte_at_a_time::AttackError) -> Self {
+        Error::ByteAtATime(e)
+    }
+}
+
+impl From<FlipError> for Error {
+    fn from(e: FlipError) -> Self {
+        Error::CbcBitflip(e)
+    }
+}
+
+impl From<length_extension::AttackError> for Error {
+    fn from(e: length_extension::AttackError) -> Self {
+        Error::LengthExtension(e)
+    }
+}
+
+impl From<timing_leak::AttackError> for Error {
+    fn from(e: timing_leak::AttackError) -> Self {
+        Error::TimingLeak(e)
+    }
+}
+
+impl From<CloneError> for Error {
+    fn from(e: CloneError) -> Self {
+        Error::MtClone(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::IoError(e)
+    }
+}
+
+/// Checks that `len` is a whole number of blocks
+pub(crate) fn check_aligned(len: usize, block_len: usize) -> Result<(), Error> {
+    if block_len == 0 || !len.is_multiple_of(block_len) {
+        return Err(Error::NotBlockAligned { len, block_len });
+    }
+    Ok(())
+}
+
+/// Checks that an IV is one block long
+pub(crate) fn check_iv(iv: &[u8], block_len: usize) -> Result<(), Error> {
+    if iv.len() != block_len {
+        return Err(Error::BadIvLength {
+            expected: block_len,
+            actual: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hamming_distance, pkcs7_unpad};
+    use std::error::Error as _;
+
+    fn unpad_and_compare(buf: &mut Vec<u8>, other: &[u8]) -> Result<u64, Error> {
+        pkcs7_unpad(buf, 16)?;
+        Ok(hamming_distance(buf, other)?)
+    }
+
+    #[test]
+    fn test_conversions() {
+        let mut buf = b"YELLOW SUBMARINE".to_vec();
+        let err = unpad_and_compare(&mut buf, b"").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Padding(PaddingError::BadPaddingByte(b'E'))
+        ));
+        assert!(err.source().is_some());
+
+        let mut buf = b"YELLOW\x0a\x0a\x0a\x0a\x0a\x0a\x0a\x0a\x0a\x0a".to_vec();
+        let err = unpad_and_compare(&mut buf, b"").unwrap_err();
+        assert!(matches!(err, Error::Hamming(_)));
+        assert_eq!(buf, b"YELLOW");
+    }
+
+    #[test]
+    fn test_display() {
+        let err = Error::NotBlockAligned {
+            len: 20,
+            block_len: 16,
+        };
+        assert_eq!(
+            err.to_string(),
+            "length 20 is not a multiple of the block length 16"
+        );
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_misaligned_padding() {
+        let mut buf = b"YELLOW\x01".to_vec();
+        assert!(matches!(
+            unpad_and_compare(&mut buf, b""),
+            Err(Error::NotBlockAligned {
+                len: 7,
+                block_len: 16
+            })
+        ));
+    }
+}
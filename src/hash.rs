@@ -0,0 +1,218 @@
+//! Hash functions, written out by hand so their internal state can be read and resumed.
+//!
+//! These are Merkle-Damgård constructions: the message is padded to a whole number of blocks and
+//! fed through a compression function, and the digest is simply the final chaining state.
+//! Nothing stops anyone from carrying on hashing from a digest, which is what length-extension
+//! attacks exploit; [`HashFunction::from_state`] does exactly that.
+
+pub mod md4;
+pub mod sha1;
+pub mod sha256;
+
+use crate::{Endianness, Error};
+use std::convert::{TryFrom, TryInto};
+
+/// Every hash in here works on 64 byte blocks
+pub const BLOCK_LEN: usize = 64;
+
+pub trait HashFunction: Clone + Default {
+    const DIGEST_LEN: usize;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    /// Resumes hashing from the digest of some message, as if `processed` bytes (the message
+    /// and its padding) had already been fed in. Fails if the digest is the wrong length or
+    /// `processed` isn't a whole number of blocks.
+    fn from_state(digest: &[u8], processed: u64) -> Result<Self, Error>;
+
+    /// The padding the hash appends to a message of `message_len` bytes before finishing
+    fn padding(message_len: u64) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::default();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// Merkle-Damgård strengthening: a 1 bit, zeros until 8 bytes short of a block boundary, then
+/// the message length in bits as a 64-bit integer
+pub fn md_padding(message_len: u64, endianness: Endianness) -> Vec<u8> {
+    let zeros = (2 * BLOCK_LEN - 9 - (message_len % BLOCK_LEN as u64) as usize) % BLOCK_LEN;
+    let mut padding = vec![0u8; 1 + zeros + 8];
+    padding[0] = 0x80;
+    // lengths are mod 2^64 bits
+    let bits = message_len.wrapping_mul(8);
+    padding[1 + zeros..].copy_from_slice(&match endianness {
+        Endianness::Big => bits.to_be_bytes(),
+        Endianness::Little => bits.to_le_bytes(),
+    });
+    padding
+}
+
+/// Checks a digest and processed length handed to [`HashFunction::from_state`]
+pub(crate) fn check_state(digest: &[u8], digest_len: usize, processed: u64) -> Result<(), Error> {
+    if digest.len() != digest_len {
+        return Err(Error::LengthMismatch {
+            expected: digest_len,
+            actual: digest.len(),
+        });
+    }
+    // checked as a u64, since a usize may be narrower
+    if !processed.is_multiple_of(BLOCK_LEN as u64) {
+        return Err(Error::NotBlockAligned {
+            len: usize::try_from(processed).unwrap_or(usize::MAX),
+            block_len: BLOCK_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// Collects input into whole blocks for a compression function, and counts it
+#[derive(Clone)]
+pub(crate) struct Blocks {
+    buf: [u8; BLOCK_LEN],
+    filled: usize,
+    len: u64,
+}
+
+impl Blocks {
+    /// `len` bytes have already been compressed
+    pub(crate) fn new(len: u64) -> Self {
+        Blocks {
+            buf: [0; BLOCK_LEN],
+            filled: 0,
+            len,
+        }
+    }
+
+    /// Total bytes fed in so far
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub(crate) fn update<F: FnMut(&[u8; BLOCK_LEN])>(&mut self, mut data: &[u8], mut compress: F) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+        if self.filled > 0 {
+            let take = (BLOCK_LEN - self.filled).min(data.len());
+            self.buf[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled < BLOCK_LEN {
+                return;
+            }
+            compress(&self.buf);
+            self.filled = 0;
+        }
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            compress(block.try_into().unwrap());
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::md4::Md4;
+    use super::sha1::Sha1;
+    use super::sha256::Sha256;
+    use super::*;
+
+    /// Splitting the input across updates, including at and around block boundaries, changes
+    /// nothing
+    fn check_streaming<H: HashFunction>() {
+        let msg: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        let expected = H::digest(&msg);
+        for split in [0, 1, 55, 56, 63, 64, 65, 128, 299, 300].iter() {
+            let mut hasher = H::default();
+            hasher.update(&msg[..*split]);
+            hasher.update(&msg[*split..]);
+            assert_eq!(hasher.finalize(), expected, "{}", split);
+        }
+    }
+
+    /// Resuming from a digest gives the digest of the message, its padding and the suffix
+    fn check_from_state<H: HashFunction>() {
+        let msg = b"comment1=cooking%20MCs;userdata=foo";
+        let digest = H::digest(msg);
+        let mut glued = msg.to_vec();
+        glued.extend(H::padding(msg.len() as u64));
+
+        let mut resumed = H::from_state(&digest, glued.len() as u64).unwrap();
+        resumed.update(b";admin=true");
+        glued.extend_from_slice(b";admin=true");
+        assert_eq!(resumed.finalize(), H::digest(&glued));
+
+        assert!(H::from_state(&digest[..H::DIGEST_LEN - 1], 64).is_err());
+        assert!(H::from_state(&digest, 63).is_err());
+    }
+
+    #[test]
+    fn test_streaming() {
+        check_streaming::<Md4>();
+        check_streaming::<Sha1>();
+        check_streaming::<Sha256>();
+    }
+
+    #[test]
+    fn test_from_state() {
+        check_from_state::<Md4>();
+        check_from_state::<Sha1>();
+        check_from_state::<Sha256>();
+    }
+
+    #[test]
+    fn test_md_padding() {
+        for len in 0..200u64 {
+            let padding = md_padding(len, Endianness::Big);
+            assert!((len + padding.len() as u64).is_multiple_of(64), "{}", len);
+            assert!(padding.len() >= 9 && padding.len() <= 72);
+            assert_eq!(padding[0], 0x80);
+        }
+        assert_eq!(
+            md_padding(55, Endianness::Big),
+            hex::decode("8000000000000001b8").unwrap()
+        );
+        assert_eq!(
+            md_padding(55, Endianness::Little),
+            hex::decode("80b801000000000000").unwrap()
+        );
+        assert_eq!(md_padding(56, Endianness::Big).len(), 72);
+        assert_eq!(md_padding(64, Endianness::Big).len(), 64);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let mut seen = Vec::new();
+        let mut blocks = Blocks::new(0);
+        for chunk in [&[1u8; 10][..], &[2; 60], &[3; 130]].iter() {
+            blocks.update(chunk, |b| seen.push(b.to_vec()));
+        }
+        assert_eq!(blocks.len(), 200);
+        assert_eq!(seen.len(), 3);
+        assert_eq!(&seen[0][..12], &[1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2]);
+        assert_eq!(&seen[1][..7], &[2, 2, 2, 2, 2, 2, 3]);
+        assert_eq!(blocks.filled, 8);
+    }
+
+    #[test]
+    fn test_check_state() {
+        assert!(check_state(&[0; 20], 20, 128).is_ok());
+        assert!(matches!(
+            check_state(&[0; 16], 20, 128),
+            Err(Error::LengthMismatch { .. })
+        ));
+        assert!(matches!(
+            check_state(&[0; 20], 20, 100),
+            Err(Error::NotBlockAligned { .. })
+        ));
+        // past what a 32-bit usize holds
+        assert!(check_state(&[0; 20], 20, (1 << 32) + 64).is_ok());
+        assert!(check_state(&[0; 20], 20, (1 << 32) + 1).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! MD4 (RFC 1320). Thoroughly broken, but still found inside legacy protocols.
+
+use super::{check_state, md_padding, Blocks, HashFunction, BLOCK_LEN};
+use crate::{Endianness, Error};
+use std::convert::TryInto;
+
+pub const DIGEST_LEN: usize = 16;
+
+const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// Order each round reads the message words in
+const ORDER: [[usize; 16]; 3] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+    [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+];
+/// Left rotations of each round, repeating every 4 steps
+const SHIFTS: [[u32; 4]; 3] = [[3, 7, 11, 19], [3, 5, 9, 13], [3, 9, 11, 15]];
+const CONSTANTS: [u32; 3] = [0, 0x5a827999, 0x6ed9eba1];
+
+#[derive(Clone)]
+pub struct Md4 {
+    state: [u32; 4],
+    blocks: Blocks,
+}
+
+impl Md4 {
+    pub fn new() -> Self {
+        Md4 {
+            state: INITIAL_STATE,
+            blocks: Blocks::new(0),
+        }
+    }
+}
+
+impl Default for Md4 {
+    fn default() -> Self {
+        Md4::new()
+    }
+}
+
+impl HashFunction for Md4 {
+    const DIGEST_LEN: usize = DIGEST_LEN;
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| compress(state, block));
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Md4::padding(self.blocks.len());
+        self.update(&padding);
+        self.state.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    fn from_state(digest: &[u8], processed: u64) -> Result<Self, Error> {
+        check_state(digest, DIGEST_LEN, processed)?;
+        let mut state = [0u32; 4];
+        for (word, bytes) in state.iter_mut().zip(digest.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Ok(Md4 {
+            state,
+            blocks: Blocks::new(processed),
+        })
+    }
+
+    /// Little-endian length, unlike SHA-1
+    fn padding(message_len: u64) -> Vec<u8> {
+        md_padding(message_len, Endianness::Little)
+    }
+}
+
+fn compress(state: &mut [u32; 4], block: &[u8; BLOCK_LEN]) {
+    let mut x = [0u32; 16];
+    for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let [mut a, mut b, mut c, mut d] = *state;
+    for round in 0..3 {
+        for (i, &k) in ORDER[round].iter().enumerate() {
+            let f = match round {
+                0 => (b & c) | (!b & d),
+                1 => (b & c) | (b & d) | (c & d),
+                _ => b ^ c ^ d,
+            };
+            let t = a
+                .wrapping_add(f)
+                .wrapping_add(x[k])
+                .wrapping_add(CONSTANTS[round])
+                .rotate_left(SHIFTS[round][i % 4]);
+            // each step updates the next register along: a, d, c, b, a, ...
+            a = d;
+            d = c;
+            c = b;
+            b = t;
+        }
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d].iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_vectors() {
+        // RFC 1320 appendix A.5
+        for &(msg, digest) in [
+            (&b""[..], hex!("31d6cfe0d16ae931b73c59d7e0c089c0")),
+            (b"a", hex!("bde52cb31de33e46245e05fbdbd6fb24")),
+            (b"abc", hex!("a448017aaf21d8525fc10ae87aa6729d")),
+            (b"message digest", hex!("d9130a8164549fe818874806e1c7014b")),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                hex!("d79e1c308aa5bbcdeea8ed63df412da9"),
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                hex!("043f8582f241db351ce627e153e7f0e4"),
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                hex!("e33b4ddc9c38f2199c3e7b164fcc0536"),
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(Md4::digest(msg), digest);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        // MD4 writes the length little-endian, unlike SHA
+        let padding = Md4::padding(35);
+        assert_eq!(&padding[padding.len() - 8..], &(8 * 35u64).to_le_bytes());
+    }
+}
@@ -0,0 +1,131 @@
+//! SHA-1 (FIPS 180-4). Broken for collisions, but that's not what it's here for.
+
+use super::{check_state, md_padding, Blocks, HashFunction, BLOCK_LEN};
+use crate::{Endianness, Error};
+use std::convert::TryInto;
+
+pub const DIGEST_LEN: usize = 20;
+
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+#[derive(Clone)]
+pub struct Sha1 {
+    state: [u32; 5],
+    blocks: Blocks,
+}
+
+impl Sha1 {
+    pub fn new() -> Self {
+        Sha1 {
+            state: INITIAL_STATE,
+            blocks: Blocks::new(0),
+        }
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1::new()
+    }
+}
+
+impl HashFunction for Sha1 {
+    const DIGEST_LEN: usize = DIGEST_LEN;
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| compress(state, block));
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let padding = Sha1::padding(self.blocks.len());
+        self.update(&padding);
+        self.state.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+
+    fn from_state(digest: &[u8], processed: u64) -> Result<Self, Error> {
+        check_state(digest, DIGEST_LEN, processed)?;
+        let mut state = [0u32; 5];
+        for (word, bytes) in state.iter_mut().zip(digest.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        Ok(Sha1 {
+            state,
+            blocks: Blocks::new(processed),
+        })
+    }
+
+    /// Big-endian length
+    fn padding(message_len: u64) -> Vec<u8> {
+        md_padding(message_len, Endianness::Big)
+    }
+}
+
+fn compress(state: &mut [u32; 5], block: &[u8; BLOCK_LEN]) {
+    let mut w = [0u32; 80];
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, &word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5a827999),
+            20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+        *s = s.wrapping_add(*v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_vectors() {
+        for &(msg, digest) in [
+            (&b""[..], hex!("da39a3ee5e6b4b0d3255bfef95601890afd80709")),
+            (b"abc", hex!("a9993e364706816aba3e25717850c26c9cd0d89d")),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                hex!("84983e441c3bd26ebaae4aa1f95129e5e54670f1"),
+            ),
+            (
+                b"The quick brown fox jumps over the lazy dog",
+                hex!("2fd4e1c67a2d28fced849ee1bb76e7391b93eb12"),
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(Sha1::digest(msg), digest);
+        }
+
+        let mut hasher = Sha1::new();
+        for _ in 0..1000 {
+            hasher.update(&[b'a'; 1000]);
+        }
+        assert_eq!(
+            hasher.finalize(),
+            hex!("34aa973cd4c4daa4f61eeb2bdbad27316534016f")
+        );
+    }
+}
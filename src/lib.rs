@@ -1,51 +1,89 @@
+pub mod aes_cbc;
+pub mod analysis;
+pub mod attacks;
 pub mod cos_sim;
+pub mod ct;
+pub mod data;
+pub mod encoding;
+pub mod error;
+pub mod hash;
 pub mod io_utils;
-pub mod aes_cbc;
+pub mod mac;
+pub mod modes;
+pub mod mt19937;
+pub mod ngram;
+pub mod oracle;
+pub mod padding;
+pub mod repeating_xor;
+pub mod scoring;
 
-use cos_sim::CharFreq;
+pub use error::Error;
+
+use scoring::Scorer;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+/// Byte order of an integer field, such as a CTR counter or a hash's message length
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
 pub struct BruteForceResult {
     pub key: u8,
-    /// score range is 0-1 inclusive. higher scores are more likely
+    /// higher scores are more likely. the range depends on the scorer
     pub score: f64,
-    pub plaintext: String,
+    pub plaintext: Vec<u8>,
+}
+
+impl BruteForceResult {
+    /// The plaintext for display, with invalid UTF-8 replaced
+    pub fn plaintext_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.plaintext)
+    }
 }
 
 fn xor(in_bytes: &[u8], key: u8) -> Vec<u8> {
     in_bytes.iter().copied().map(|x| x ^ key).collect()
 }
 
-/// Returns possible plaintexts unsorted
-pub fn brute_force_single_byte_xor(
+/// Returns all 256 candidates, most likely first
+pub fn brute_force_single_byte_xor<S: Scorer + ?Sized>(
     ciphertext: &[u8],
-    reference_freqs: &CharFreq,
+    scorer: &S,
 ) -> Vec<BruteForceResult> {
-    (0..=255)
-        .flat_map(|key| {
-            String::from_utf8(xor(ciphertext, key))
-                .ok()
-                .and_then(|plaintext| {
-                    CharFreq::from_str(&plaintext)
-                        .ok()
-                        .map(|freq| BruteForceResult {
-                            key,
-                            score: freq.cosine_similarity(reference_freqs),
-                            plaintext,
-                        })
-                })
+    let mut results: Vec<BruteForceResult> = (0..=255)
+        .map(|key| {
+            let plaintext = xor(ciphertext, key);
+            BruteForceResult {
+                key,
+                score: scorer.score(&plaintext),
+                plaintext,
+            }
         })
-        .collect()
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
 }
 
+/// Panics if `key` is empty
 pub fn repeating_key_xor(plaintext: &[u8], key: &[u8]) -> Vec<u8> {
-    assert!(key.len() < plaintext.len());
-    plaintext
+    try_repeating_key_xor(plaintext, key).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// The key may be longer than the plaintext, in which case only its start is used
+pub fn try_repeating_key_xor(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.is_empty() {
+        return Err(Error::EmptyKey);
+    }
+    Ok(plaintext
         .iter()
         .zip(key.iter().cycle())
         .map(|(x, y)| x ^ y)
-        .collect()
+        .collect())
 }
 
 #[derive(Debug)]
@@ -63,6 +101,8 @@ impl Display for HammingError {
     }
 }
 
+impl std::error::Error for HammingError {}
+
 /// Larger number means less similar
 pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u64, HammingError> {
     if a.len() != b.len() {
@@ -76,11 +116,115 @@ pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u64, HammingError> {
     Ok(dist)
 }
 
+/// Number of blocks that are identical to some earlier block. Trailing bytes that don't fill a
+/// block are ignored, as is everything if `block_len` is 0.
+pub fn count_repeated_blocks(ciphertext: &[u8], block_len: usize) -> usize {
+    if block_len == 0 {
+        return 0;
+    }
+    let mut seen = HashSet::new();
+    ciphertext
+        .chunks_exact(block_len)
+        .filter(|block| !seen.insert(*block))
+        .count()
+}
+
+/// Indices of blocks that are identical to the block immediately after them
+pub fn adjacent_repeated_blocks(ciphertext: &[u8], block_len: usize) -> Vec<usize> {
+    if block_len == 0 {
+        return Vec::new();
+    }
+    let blocks: Vec<&[u8]> = ciphertext.chunks_exact(block_len).collect();
+    blocks
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] == pair[1])
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// ECB encrypts identical plaintext blocks to identical ciphertext blocks, so any repeat is a
+/// strong sign of ECB
+pub fn detect_ecb(ciphertext: &[u8], block_len: usize) -> bool {
+    count_repeated_blocks(ciphertext, block_len) > 0
+}
+
+/// Panics if `len` is 0 or over 255
 pub fn pkcs7_pad(buf: &mut Vec<u8>, len: usize) {
+    try_pkcs7_pad(buf, len).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Fails if `len` is 0, or too long to be written in the padding byte
+pub fn try_pkcs7_pad(buf: &mut Vec<u8>, len: usize) -> Result<(), PaddingError> {
+    if len == 0 || len > u8::MAX as usize {
+        return Err(PaddingError::BadBlockLength(len));
+    }
     let to_add = len - (buf.len() % len);
     for _ in 0..to_add {
         buf.push(to_add as u8);
     }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaddingError {
+    EmptyInput,
+    /// the last byte is zero or larger than the block length
+    BadPaddingByte(u8),
+    /// not every padding byte matches the last byte
+    InconsistentPadding,
+    NotBlockAligned {
+        len: usize,
+        block_len: usize,
+    },
+    /// the IV isn't one block long, so the request was malformed before padding came into it
+    BadIvLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// the block length is 0, or too long for the padding scheme to record
+    BadBlockLength(usize),
+}
+
+impl Display for PaddingError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            PaddingError::EmptyInput => write!(f, "empty input"),
+            PaddingError::BadPaddingByte(b) => write!(f, "bad padding byte: {:#X}", b),
+            PaddingError::InconsistentPadding => write!(f, "inconsistent padding bytes"),
+            PaddingError::NotBlockAligned { len, block_len } => write!(
+                f,
+                "length {} is not a multiple of the block length {}",
+                len, block_len
+            ),
+            PaddingError::BadIvLength { expected, actual } => {
+                write!(f, "IV must be {} bytes, got {}", expected, actual)
+            }
+            PaddingError::BadBlockLength(len) => write!(f, "bad block length: {}", len),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+/// Strips PKCS#7 padding in place. `buf` is left untouched if the padding is invalid.
+pub fn pkcs7_unpad(buf: &mut Vec<u8>, len: usize) -> Result<(), PaddingError> {
+    let pad = *buf.last().ok_or(PaddingError::EmptyInput)?;
+    if !buf.len().is_multiple_of(len) {
+        return Err(PaddingError::NotBlockAligned {
+            len: buf.len(),
+            block_len: len,
+        });
+    }
+    if pad == 0 || pad as usize > len {
+        return Err(PaddingError::BadPaddingByte(pad));
+    }
+    let new_len = buf.len() - pad as usize;
+    if buf[new_len..].iter().any(|&b| b != pad) {
+        return Err(PaddingError::InconsistentPadding);
+    }
+    buf.truncate(new_len);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -99,6 +243,26 @@ mod tests {
         assert_eq!(ciphertext, TEST_VEC_CIPHER);
     }
 
+    #[test]
+    fn test_try_repeating_key_xor() {
+        assert_eq!(try_repeating_key_xor(b"hi", b"ICE").unwrap(), b"\x21\x2a");
+        assert_eq!(try_repeating_key_xor(b"", b"ICE").unwrap(), b"");
+        assert!(matches!(
+            try_repeating_key_xor(b"hi", b""),
+            Err(Error::EmptyKey)
+        ));
+    }
+
+    #[test]
+    fn test_brute_force_keeps_every_key() {
+        let results = brute_force_single_byte_xor(b"\xff\x00 not UTF-8", &scoring::PrintableRatio);
+        assert_eq!(results.len(), 256);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+        let mut keys: Vec<u8> = results.iter().map(|r| r.key).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..=255).collect::<Vec<u8>>());
+    }
+
     #[test]
     fn test_hamming() {
         let a = "this is a test";
@@ -106,10 +270,92 @@ mod tests {
         assert_eq!(hamming_distance(a.as_bytes(), b.as_bytes()).unwrap(), 37);
     }
 
+    #[test]
+    fn test_count_repeated_blocks() {
+        assert_eq!(count_repeated_blocks(b"aaaabbbbaaaaccccaaaa", 4), 2);
+        assert_eq!(count_repeated_blocks(b"aaaabbbbccccaa", 4), 0);
+        assert!(detect_ecb(b"aaaabbbbaaaa", 4));
+        assert!(!detect_ecb(b"", 4));
+        assert!(!detect_ecb(b"aaaa", 0));
+        assert!(adjacent_repeated_blocks(b"aaaa", 0).is_empty());
+    }
+
+    #[test]
+    fn test_adjacent_repeated_blocks() {
+        assert_eq!(
+            adjacent_repeated_blocks(b"aaaabbbbbbbbaaaacccccccccccc", 4),
+            vec![1, 4, 5]
+        );
+        assert!(adjacent_repeated_blocks(b"aaaabbbbaaaa", 4).is_empty());
+    }
+
     #[test]
     fn test_pkcs7_pad() {
         let mut input: Vec<u8> = Vec::from(b"YELLOW SUBMARINE".to_owned());
         pkcs7_pad(&mut input, 20);
         assert_eq!(input, b"YELLOW SUBMARINE\x04\x04\x04\x04");
     }
+
+    #[test]
+    fn test_pkcs7_unpad() {
+        let mut input = b"ICE ICE BABY\x04\x04\x04\x04".to_vec();
+        assert_eq!(pkcs7_unpad(&mut input, 16), Ok(()));
+        assert_eq!(input, b"ICE ICE BABY");
+
+        let mut input = [16; 16].to_vec();
+        assert_eq!(pkcs7_unpad(&mut input, 16), Ok(()));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_pkcs7_unpad_errors() {
+        let cases: [(&[u8], PaddingError); 6] = [
+            (b"", PaddingError::EmptyInput),
+            (
+                b"ICE ICE BABY\x04\x04\x04",
+                PaddingError::NotBlockAligned {
+                    len: 15,
+                    block_len: 16,
+                },
+            ),
+            (
+                b"ICE ICE BABY\x05\x05\x05\x05",
+                PaddingError::InconsistentPadding,
+            ),
+            (
+                b"ICE ICE BABY\x01\x02\x03\x04",
+                PaddingError::InconsistentPadding,
+            ),
+            (
+                b"ICE ICE BABY\x04\x04\x04\x00",
+                PaddingError::BadPaddingByte(0),
+            ),
+            (
+                b"ICE ICE BABY\x04\x04\x04\x11",
+                PaddingError::BadPaddingByte(0x11),
+            ),
+        ];
+        for (input, expected) in cases.iter() {
+            let mut buf = input.to_vec();
+            assert_eq!(pkcs7_unpad(&mut buf, 16), Err(*expected));
+            assert_eq!(&buf, input);
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_pad_block_length() {
+        let mut buf = b"ICE".to_vec();
+        assert_eq!(
+            try_pkcs7_pad(&mut buf, 0),
+            Err(PaddingError::BadBlockLength(0))
+        );
+        assert_eq!(
+            try_pkcs7_pad(&mut buf, 256),
+            Err(PaddingError::BadBlockLength(256))
+        );
+        assert_eq!(buf, b"ICE");
+        assert_eq!(try_pkcs7_pad(&mut buf, 255), Ok(()));
+        assert_eq!(buf.len(), 255);
+        assert_eq!(buf[254], 252);
+    }
 }
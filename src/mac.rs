@@ -0,0 +1,209 @@
+//! Message authentication codes built from the hashes in [`crate::hash`].
+
+use crate::ct;
+use crate::hash::{HashFunction, BLOCK_LEN};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// Answers whether `mac` is valid for `msg`. Implemented for closures.
+pub trait MacVerifier {
+    fn verify(&self, msg: &[u8], mac: &[u8]) -> bool;
+}
+
+impl<F: Fn(&[u8], &[u8]) -> bool> MacVerifier for F {
+    fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        self(msg, mac)
+    }
+}
+
+/// `H(key || msg)`. Looks reasonable, but anyone holding a MAC can extend the message without
+/// knowing the key; see [`crate::attacks::length_extension`].
+pub fn secret_prefix_mac<H: HashFunction>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut hasher = H::default();
+    hasher.update(key);
+    hasher.update(msg);
+    hasher.finalize()
+}
+
+/// A key for [`secret_prefix_mac`]
+#[derive(Clone)]
+pub struct SecretPrefixMac<H> {
+    key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: HashFunction> SecretPrefixMac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        SecretPrefixMac {
+            key: key.to_vec(),
+            hash: PhantomData,
+        }
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        secret_prefix_mac::<H>(&self.key, msg)
+    }
+
+    pub fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        ct::eq(&self.sign(msg), mac)
+    }
+}
+
+/// HMAC (RFC 2104), which length extension doesn't touch: the outer hash covers the inner one
+/// with the key mixed in again
+#[derive(Clone)]
+pub struct Hmac<H> {
+    inner: H,
+    outer: H,
+}
+
+impl<H: HashFunction> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        // keys longer than a block are hashed first, shorter ones are zero padded
+        let mut block = if key.len() > BLOCK_LEN {
+            H::digest(key)
+        } else {
+            key.to_vec()
+        };
+        block.resize(BLOCK_LEN, 0);
+
+        let mut inner = H::default();
+        inner.update(&block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+        let mut outer = H::default();
+        outer.update(&block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+        Hmac { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+pub fn hmac<H: HashFunction>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<H>::new(key);
+    mac.update(msg);
+    mac.finalize()
+}
+
+/// Compares byte by byte and stops at the first difference, waiting `delay` after each byte
+/// that matched. Deliberately insecure: how long it takes reveals how much of `a` was right.
+/// The delay is a busy wait, so even microsecond leaks are accurate. [`ct::eq`] is the fix.
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b.iter()) {
+        if x != y {
+            return false;
+        }
+        spin(delay);
+    }
+    true
+}
+
+fn spin(delay: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < delay {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha1::Sha1;
+    use crate::hash::sha256::Sha256;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_secret_prefix_mac() {
+        let mac = secret_prefix_mac::<Sha1>(b"YELLOW SUBMARINE", b"hello");
+        assert_eq!(mac, Sha1::digest(b"YELLOW SUBMARINEhello"));
+
+        let key = SecretPrefixMac::<Sha1>::new(b"YELLOW SUBMARINE");
+        assert_eq!(key.sign(b"hello"), mac);
+        assert!(key.verify(b"hello", &mac));
+        assert!(!key.verify(b"hellO", &mac));
+        assert!(!key.verify(b"hello", &mac[..19]));
+        assert!(!SecretPrefixMac::<Sha1>::new(b"ICE").verify(b"hello", &mac));
+    }
+
+    #[test]
+    fn test_hmac_sha1() {
+        // RFC 2202
+        let long = "Test Using Larger Than Block-Size Key - Hash Key First";
+        for (key, msg, mac) in [
+            (
+                vec![0x0b; 20],
+                "Hi There",
+                hex!("b617318655057264e28bc0b6fb378c8ef146be00"),
+            ),
+            (
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                hex!("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"),
+            ),
+            (
+                vec![0xaa; 80],
+                long,
+                hex!("aa4ae5e15272d00e95705637ce8a3b55ed402112"),
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(hmac::<Sha1>(key, msg.as_bytes()), mac);
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231
+        let long = "Test Using Larger Than Block-Size Key - Hash Key First";
+        for (key, msg, mac) in [
+            (
+                vec![0x0b; 20],
+                "Hi There",
+                hex!("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            ),
+            (
+                b"Jefe".to_vec(),
+                "what do ya want for nothing?",
+                hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            ),
+            (
+                vec![0xaa; 131],
+                long,
+                hex!("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"),
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(hmac::<Sha256>(key, msg.as_bytes()), mac);
+        }
+
+        // streaming gives the same answer
+        let mut mac = Hmac::<Sha256>::new(b"Jefe");
+        mac.update(b"what do ya want ");
+        mac.update(b"for nothing?");
+        assert_eq!(
+            mac.finalize(),
+            hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?")
+        );
+    }
+
+    #[test]
+    fn test_insecure_compare() {
+        assert!(insecure_compare(b"abc", b"abc", Duration::from_micros(0)));
+        assert!(!insecure_compare(b"abc", b"abd", Duration::from_micros(0)));
+        assert!(!insecure_compare(b"abc", b"ab", Duration::from_micros(0)));
+
+        let delay = Duration::from_millis(2);
+        let start = Instant::now();
+        insecure_compare(b"abcd", b"abXX", delay);
+        assert!(start.elapsed() >= 2 * delay);
+    }
+}
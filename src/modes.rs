@@ -0,0 +1,71 @@
+//! Block cipher modes of operation.
+//!
+//! Everything in here is generic over the `aes::cipher` block cipher traits, so the same call
+//! sites work for AES-128/192/256 or any other cipher that implements them. Callers construct the
+//! cipher themselves (e.g. `Aes256::new(key.into())`) and pass a reference in.
+
+pub mod cbc;
+pub mod cfb;
+pub mod ctr;
+pub mod ecb;
+pub mod ofb;
+
+use crate::Error;
+use aes::cipher::generic_array::typenum::Unsigned;
+use aes::BlockCipher;
+
+/// XORs `b` into `a`. Panics if they differ in length.
+pub fn xor_in_place(a: &mut [u8], b: &[u8]) {
+    try_xor_in_place(a, b).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// XORs `b` into `a`, which must be the same length. `a` is untouched on error.
+pub fn try_xor_in_place(a: &mut [u8], b: &[u8]) -> Result<(), Error> {
+    if a.len() != b.len() {
+        return Err(Error::LengthMismatch {
+            expected: a.len(),
+            actual: b.len(),
+        });
+    }
+    a.iter_mut().zip(b.iter()).for_each(|(l, r)| *l ^= *r);
+    Ok(())
+}
+
+/// Block length of `C` in bytes
+pub fn block_len<C: BlockCipher>() -> usize {
+    C::BlockSize::USIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_xor_in_place() {
+        let mut a = *b"abc";
+        assert!(matches!(
+            try_xor_in_place(&mut a, b"ab"),
+            Err(Error::LengthMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert_eq!(&a, b"abc");
+        try_xor_in_place(&mut a, b"abc").unwrap();
+        assert_eq!(a, [0; 3]);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_vectors {
+    //! Test vectors from NIST SP 800-38A, appendix F
+
+    use hex_literal::hex;
+
+    pub const AES128_KEY: [u8; 16] = hex!("2b7e151628aed2a6abf7158809cf4f3c");
+    pub const AES192_KEY: [u8; 24] = hex!("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b");
+    pub const AES256_KEY: [u8; 32] =
+        hex!("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4");
+    pub const IV: [u8; 16] = hex!("000102030405060708090a0b0c0d0e0f");
+    pub const PLAINTEXT: [u8; 64] = hex!("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710");
+}
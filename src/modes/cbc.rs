@@ -0,0 +1,242 @@
+//! Cipher block chaining mode
+
+use super::{block_len, xor_in_place};
+use crate::error::{check_aligned, check_iv};
+use crate::padding::Padding;
+use crate::{Error, PaddingError};
+use aes::{BlockDecrypt, BlockEncrypt};
+
+/// `msg` must be a multiple of the block length; no padding is applied. Panics otherwise, or if
+/// `iv` isn't one block long.
+pub fn encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_encrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`encrypt`], but fails instead of panicking
+pub fn try_encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_iv(iv, block_len)?;
+    check_aligned(msg.len(), block_len)?;
+
+    let mut result = msg.to_vec();
+    let mut last: &[u8] = iv;
+    for chunk in result.chunks_exact_mut(block_len) {
+        xor_in_place(chunk, last);
+        cipher.encrypt_block(chunk.into());
+        last = chunk;
+    }
+    Ok(result)
+}
+
+/// does not strip padding. Panics if `msg` isn't block-aligned or `iv` isn't one block long.
+pub fn decrypt<C: BlockDecrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_decrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt<C: BlockDecrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_iv(iv, block_len)?;
+    check_aligned(msg.len(), block_len)?;
+
+    let prev_blocks = std::iter::once(iv).chain(msg.chunks_exact(block_len));
+    let mut result = msg.to_vec();
+    for (chunk, last) in result.chunks_exact_mut(block_len).zip(prev_blocks) {
+        cipher.decrypt_block(chunk.into());
+        xor_in_place(chunk, last);
+    }
+    Ok(result)
+}
+
+/// [`check_iv`] for the functions that report [`PaddingError`]
+fn check_padded_iv(iv: &[u8], block_len: usize) -> Result<(), PaddingError> {
+    if iv.len() != block_len {
+        return Err(PaddingError::BadIvLength {
+            expected: block_len,
+            actual: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Pads `msg` and encrypts it
+pub fn encrypt_padded<C: BlockEncrypt>(
+    cipher: &C,
+    iv: &[u8],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
+    let block_len = block_len::<C>();
+    check_padded_iv(iv, block_len)?;
+    let mut padded = msg.to_vec();
+    padding.pad(&mut padded, block_len)?;
+    Ok(encrypt(cipher, iv, &padded))
+}
+
+/// Decrypts `msg` and strips its padding
+pub fn decrypt_padded<C: BlockDecrypt>(
+    cipher: &C,
+    iv: &[u8],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
+    let block_len = block_len::<C>();
+    check_padded_iv(iv, block_len)?;
+    if !msg.len().is_multiple_of(block_len) {
+        return Err(PaddingError::NotBlockAligned {
+            len: msg.len(),
+            block_len,
+        });
+    }
+    let mut result = decrypt(cipher, iv, msg);
+    padding.unpad(&mut result, block_len)?;
+    Ok(result)
+}
+
+/// Decrypts and strips PKCS#7 padding, failing the same way a server checking padding would
+pub fn decrypt_and_unpad<C: BlockDecrypt>(
+    cipher: &C,
+    iv: &[u8],
+    msg: &[u8],
+) -> Result<Vec<u8>, PaddingError> {
+    decrypt_padded(cipher, iv, msg, Padding::Pkcs7)
+}
+
+/// [`encrypt_padded`] with the crate [`Error`]
+pub fn try_encrypt_padded<C: BlockEncrypt>(
+    cipher: &C,
+    iv: &[u8],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, Error> {
+    Ok(encrypt_padded(cipher, iv, msg, padding)?)
+}
+
+/// [`decrypt_padded`] with the crate [`Error`]
+pub fn try_decrypt_padded<C: BlockDecrypt>(
+    cipher: &C,
+    iv: &[u8],
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, Error> {
+    Ok(decrypt_padded(cipher, iv, msg, padding)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::test_vectors::*;
+    use aes::{Aes128, Aes256, NewBlockCipher};
+    use hex_literal::hex;
+
+    #[test]
+    fn test_aes128() {
+        let expected = hex!("7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b273bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7");
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT), expected);
+        assert_eq!(decrypt(&cipher, &IV, &expected), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_decrypt_and_unpad() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let mut padded = PLAINTEXT[..40].to_vec();
+        crate::pkcs7_pad(&mut padded, 16);
+        let ciphertext = encrypt(&cipher, &IV, &padded);
+        assert_eq!(
+            decrypt_and_unpad(&cipher, &IV, &ciphertext),
+            Ok(PLAINTEXT[..40].to_vec())
+        );
+
+        // the NIST plaintext ends in 0x10 but isn't a full block of padding
+        let ciphertext = encrypt(&cipher, &IV, &PLAINTEXT);
+        assert_eq!(
+            decrypt_and_unpad(&cipher, &IV, &ciphertext),
+            Err(PaddingError::InconsistentPadding)
+        );
+        assert_eq!(
+            decrypt_and_unpad(&cipher, &IV, &ciphertext[..20]),
+            Err(PaddingError::NotBlockAligned {
+                len: 20,
+                block_len: 16
+            })
+        );
+        assert_eq!(
+            decrypt_and_unpad(&cipher, &IV, &[]),
+            Err(PaddingError::EmptyInput)
+        );
+    }
+
+    #[test]
+    fn test_padded_bad_iv() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let bad_iv = Err(PaddingError::BadIvLength {
+            expected: 16,
+            actual: 8,
+        });
+        assert_eq!(
+            encrypt_padded(&cipher, &IV[..8], b"YELLOW", Padding::Pkcs7),
+            bad_iv
+        );
+        assert_eq!(
+            decrypt_padded(&cipher, &IV[..8], &PLAINTEXT, Padding::Pkcs7),
+            bad_iv
+        );
+        assert_eq!(decrypt_and_unpad(&cipher, &IV[..8], &PLAINTEXT), bad_iv);
+    }
+
+    #[test]
+    fn test_padded_round_trip() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        for &padding in Padding::ALL.iter() {
+            for len in (0..=48).filter(|&len| padding != Padding::None || len % 16 == 0) {
+                let ciphertext = encrypt_padded(&cipher, &IV, &PLAINTEXT[..len], padding).unwrap();
+                assert_eq!(
+                    decrypt_padded(&cipher, &IV, &ciphertext, padding),
+                    Ok(PLAINTEXT[..len].to_vec())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_aes256() {
+        let expected = hex!("f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b");
+        let cipher = Aes256::new(&AES256_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT), expected);
+        assert_eq!(decrypt(&cipher, &IV, &expected), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_try_bad_input() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert!(matches!(
+            try_encrypt(&cipher, &IV[..8], &PLAINTEXT),
+            Err(Error::BadIvLength {
+                expected: 16,
+                actual: 8
+            })
+        ));
+        assert!(matches!(
+            try_decrypt(&cipher, &IV, &PLAINTEXT[..20]),
+            Err(Error::NotBlockAligned {
+                len: 20,
+                block_len: 16
+            })
+        ));
+        assert!(matches!(
+            try_decrypt_padded(&cipher, &IV, &PLAINTEXT, Padding::Pkcs7),
+            Err(Error::Padding(PaddingError::BadPaddingByte(_)))
+        ));
+        assert!(matches!(
+            try_encrypt_padded(&cipher, &[], b"YELLOW", Padding::Pkcs7),
+            Err(Error::BadIvLength { .. })
+        ));
+
+        let ciphertext = try_encrypt_padded(&cipher, &IV, b"YELLOW", Padding::Pkcs7).unwrap();
+        assert_eq!(
+            try_decrypt_padded(&cipher, &IV, &ciphertext, Padding::Pkcs7).unwrap(),
+            b"YELLOW"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Full-block cipher feedback mode (CFB-128 for AES). Works on messages of any length.
+
+use super::{block_len, xor_in_place};
+use crate::error::check_iv;
+use crate::Error;
+use aes::BlockEncrypt;
+
+/// Panics if `iv` isn't one block long
+pub fn encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_encrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`encrypt`], but fails instead of panicking
+pub fn try_encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_iv(iv, block_len)?;
+
+    let mut result = msg.to_vec();
+    let mut feedback = iv.to_vec();
+    for chunk in result.chunks_mut(block_len) {
+        cipher.encrypt_block(feedback.as_mut_slice().into());
+        xor_in_place(chunk, &feedback[..chunk.len()]);
+        feedback[..chunk.len()].copy_from_slice(chunk);
+    }
+    Ok(result)
+}
+
+/// Panics if `iv` isn't one block long
+pub fn decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_decrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_iv(iv, block_len)?;
+
+    let mut result = msg.to_vec();
+    let mut feedback = iv.to_vec();
+    for (chunk, ct) in result.chunks_mut(block_len).zip(msg.chunks(block_len)) {
+        cipher.encrypt_block(feedback.as_mut_slice().into());
+        xor_in_place(chunk, &feedback[..chunk.len()]);
+        feedback[..ct.len()].copy_from_slice(ct);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::test_vectors::*;
+    use aes::{Aes128, NewBlockCipher};
+    use hex_literal::hex;
+
+    const EXPECTED: [u8; 64] = hex!("3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6");
+
+    #[test]
+    fn test_aes128() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT), EXPECTED);
+        assert_eq!(decrypt(&cipher, &IV, &EXPECTED), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_partial_block() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT[..40]), &EXPECTED[..40]);
+        assert_eq!(decrypt(&cipher, &IV, &EXPECTED[..40]), &PLAINTEXT[..40]);
+    }
+
+    #[test]
+    fn test_try_bad_iv() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert!(matches!(
+            try_encrypt(&cipher, &IV[1..], &PLAINTEXT),
+            Err(Error::BadIvLength {
+                expected: 16,
+                actual: 15
+            })
+        ));
+        assert!(try_decrypt(&cipher, &[], &PLAINTEXT).is_err());
+        assert_eq!(try_decrypt(&cipher, &IV, &EXPECTED).unwrap(), PLAINTEXT);
+    }
+}
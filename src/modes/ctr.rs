@@ -0,0 +1,286 @@
+//! Counter mode. Encryption and decryption are the same operation.
+//!
+//! The counter block is `nonce || counter`. How many bytes belong to the counter, and which byte
+//! order it is stored in, is described by a [`CounterLayout`]. The module-level `encrypt` and
+//! `decrypt` treat the IV as the initial counter block and increment the whole block as a
+//! big-endian integer (the NIST SP 800-38A layout). Use [`Ctr`] for anything else, or for
+//! random access into the keystream.
+
+use super::{block_len, xor_in_place};
+use crate::error::check_iv;
+use crate::Error;
+use aes::BlockEncrypt;
+
+pub use crate::Endianness;
+
+/// Describes the counter field at the end of the counter block. Everything before it is nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterLayout {
+    /// length of the counter field in bytes, at most the block length (and at most 16)
+    pub counter_len: usize,
+    pub endianness: Endianness,
+}
+
+impl CounterLayout {
+    /// The whole 128-bit block is a big-endian counter
+    pub const NIST: CounterLayout = CounterLayout {
+        counter_len: 16,
+        endianness: Endianness::Big,
+    };
+    /// 64-bit nonce followed by a 64-bit little-endian block counter
+    pub const CRYPTOPALS: CounterLayout = CounterLayout {
+        counter_len: 8,
+        endianness: Endianness::Little,
+    };
+}
+
+/// A CTR keystream with a byte position that can be moved freely
+pub struct Ctr<'a, C: BlockEncrypt> {
+    cipher: &'a C,
+    initial: Vec<u8>,
+    layout: CounterLayout,
+    position: u64,
+}
+
+impl<'a, C: BlockEncrypt> Ctr<'a, C> {
+    /// `initial` is the full counter block for byte offset 0. Panics if it isn't one block long,
+    /// or the layout's counter doesn't fit in it.
+    pub fn new(cipher: &'a C, initial: &[u8], layout: CounterLayout) -> Self {
+        Ctr::try_new(cipher, initial, layout).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`Ctr::new`], but fails instead of panicking
+    pub fn try_new(cipher: &'a C, initial: &[u8], layout: CounterLayout) -> Result<Self, Error> {
+        check_iv(initial, block_len::<C>())?;
+        if layout.counter_len > initial.len() || layout.counter_len > 16 {
+            return Err(Error::BadCounterLength(layout.counter_len));
+        }
+        Ok(Ctr {
+            cipher,
+            initial: initial.to_vec(),
+            layout,
+            position: 0,
+        })
+    }
+
+    /// Builds the initial counter block from a nonce and a counter starting at zero. `nonce`
+    /// must fill everything in the block that isn't counter.
+    pub fn with_nonce(cipher: &'a C, nonce: &[u8], layout: CounterLayout) -> Self {
+        Ctr::try_with_nonce(cipher, nonce, layout).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`Ctr::with_nonce`], but fails instead of panicking
+    pub fn try_with_nonce(
+        cipher: &'a C,
+        nonce: &[u8],
+        layout: CounterLayout,
+    ) -> Result<Self, Error> {
+        let mut initial = nonce.to_vec();
+        initial.resize(nonce.len() + layout.counter_len, 0);
+        Ctr::try_new(cipher, &initial, layout)
+    }
+
+    /// Current byte offset into the keystream
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Moves to an arbitrary byte offset into the keystream. The position wraps back to 0
+    /// after `u64::MAX`.
+    pub fn seek(&mut self, offset: u64) {
+        self.position = offset;
+    }
+
+    /// XORs the keystream into `buf` starting at the current position, then advances past it
+    pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+        let block_len = self.initial.len();
+        let mut done = 0;
+        while done < buf.len() {
+            let block_index = self.position / block_len as u64;
+            let offset = (self.position % block_len as u64) as usize;
+            let mut keystream = self.counter_block(block_index);
+            self.cipher.encrypt_block(keystream.as_mut_slice().into());
+            let n = (block_len - offset).min(buf.len() - done);
+            xor_in_place(&mut buf[done..done + n], &keystream[offset..offset + n]);
+            done += n;
+            self.position = self.position.wrapping_add(n as u64);
+        }
+    }
+
+    /// The counter block for the given block index. The counter wraps within its field and
+    /// never carries into the nonce.
+    fn counter_block(&self, block_index: u64) -> Vec<u8> {
+        let mut block = self.initial.clone();
+        let nonce_len = block.len() - self.layout.counter_len;
+        let field = &mut block[nonce_len..];
+        let value = read_counter(field, self.layout.endianness).wrapping_add(block_index as u128);
+        write_counter(field, value, self.layout.endianness);
+        block
+    }
+}
+
+fn read_counter(field: &[u8], endianness: Endianness) -> u128 {
+    let fold = |acc: u128, &b: &u8| (acc << 8) | b as u128;
+    match endianness {
+        Endianness::Big => field.iter().fold(0, fold),
+        Endianness::Little => field.iter().rev().fold(0, fold),
+    }
+}
+
+/// writes the low `field.len()` bytes of `value`
+fn write_counter(field: &mut [u8], mut value: u128, endianness: Endianness) {
+    let mut store = |b: &mut u8| {
+        *b = value as u8;
+        value >>= 8;
+    };
+    match endianness {
+        Endianness::Big => field.iter_mut().rev().for_each(&mut store),
+        Endianness::Little => field.iter_mut().for_each(&mut store),
+    }
+}
+
+/// Panics if `iv` isn't one block long
+pub fn encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_encrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`encrypt`], but fails instead of panicking
+pub fn try_encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let layout = CounterLayout {
+        counter_len: block_len::<C>().min(16),
+        endianness: Endianness::Big,
+    };
+    let mut result = msg.to_vec();
+    Ctr::try_new(cipher, iv, layout)?.apply_keystream(&mut result);
+    Ok(result)
+}
+
+/// Panics if `iv` isn't one block long
+pub fn decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    encrypt(cipher, iv, msg)
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    try_encrypt(cipher, iv, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::test_vectors::*;
+    use aes::{Aes128, NewBlockCipher};
+    use hex_literal::hex;
+
+    const COUNTER: [u8; 16] = hex!("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    const EXPECTED: [u8; 64] = hex!("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee");
+
+    #[test]
+    fn test_aes128() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &COUNTER, &PLAINTEXT), EXPECTED);
+        assert_eq!(decrypt(&cipher, &COUNTER, &EXPECTED), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_cryptopals_layout() {
+        let ciphertext = base64::decode(
+            "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        )
+        .unwrap();
+        let cipher = Aes128::new(b"YELLOW SUBMARINE".into());
+        let mut ctr = Ctr::with_nonce(&cipher, &[0; 8], CounterLayout::CRYPTOPALS);
+        let mut buf = ciphertext.clone();
+        ctr.apply_keystream(&mut buf);
+        assert_eq!(
+            buf,
+            &b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "[..]
+        );
+        assert_eq!(ctr.position(), ciphertext.len() as u64);
+    }
+
+    #[test]
+    fn test_seek() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let mut ctr = Ctr::new(&cipher, &COUNTER, CounterLayout::NIST);
+        for start in [0, 1, 15, 16, 17, 33, 63].iter().copied() {
+            for end in start..=EXPECTED.len() {
+                let mut buf = EXPECTED[start..end].to_vec();
+                ctr.seek(start as u64);
+                ctr.apply_keystream(&mut buf);
+                assert_eq!(buf, &PLAINTEXT[start..end]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_position_wraps() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let mut ctr = Ctr::new(&cipher, &COUNTER, CounterLayout::NIST);
+        let mut start = [0u8; 16];
+        ctr.apply_keystream(&mut start);
+
+        // the last byte of the keystream, then back to the start
+        let mut buf = [0u8; 17];
+        ctr.seek(u64::MAX);
+        ctr.apply_keystream(&mut buf);
+        assert_eq!(ctr.position(), 16);
+        assert_eq!(buf[1..], start);
+    }
+
+    #[test]
+    fn test_counter_carry() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let layout = CounterLayout {
+            counter_len: 2,
+            endianness: Endianness::Little,
+        };
+        let ctr = Ctr::new(&cipher, &hex!("aaaaaaaaaaaaaaaaaaaaaaaaaaaafeff"), layout);
+        assert_eq!(
+            ctr.counter_block(3),
+            hex!("aaaaaaaaaaaaaaaaaaaaaaaaaaaa0100")
+        );
+        // wraps within the field without touching the nonce
+        let ctr = Ctr::new(&cipher, &hex!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaffff"), layout);
+        assert_eq!(
+            ctr.counter_block(1),
+            hex!("aaaaaaaaaaaaaaaaaaaaaaaaaaaa0000")
+        );
+    }
+
+    #[test]
+    fn test_big_endian_counter() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        let layout = CounterLayout {
+            counter_len: 4,
+            endianness: Endianness::Big,
+        };
+        let ctr = Ctr::with_nonce(&cipher, &[0x11; 12], layout);
+        assert_eq!(
+            ctr.counter_block(0x0102),
+            hex!("11111111111111111111111100000102")
+        );
+    }
+
+    #[test]
+    fn test_try_bad_input() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert!(matches!(
+            try_encrypt(&cipher, &COUNTER[..12], &PLAINTEXT),
+            Err(Error::BadIvLength { .. })
+        ));
+        let layout = CounterLayout {
+            counter_len: 17,
+            endianness: Endianness::Big,
+        };
+        assert!(matches!(
+            Ctr::try_with_nonce(&cipher, &[], layout),
+            Err(Error::BadIvLength { .. })
+        ));
+        assert!(matches!(
+            Ctr::try_new(&cipher, &COUNTER, layout),
+            Err(Error::BadCounterLength(17))
+        ));
+        assert!(Ctr::try_with_nonce(&cipher, &[0; 8], CounterLayout::CRYPTOPALS).is_ok());
+    }
+}
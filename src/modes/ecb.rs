@@ -0,0 +1,131 @@
+//! Electronic codebook mode. Every block is encrypted independently.
+
+use super::block_len;
+use crate::error::check_aligned;
+use crate::padding::Padding;
+use crate::{Error, PaddingError};
+use aes::{BlockDecrypt, BlockEncrypt};
+
+/// `msg` must be a multiple of the block length; no padding is applied. Panics otherwise.
+pub fn encrypt<C: BlockEncrypt>(cipher: &C, msg: &[u8]) -> Vec<u8> {
+    try_encrypt(cipher, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`encrypt`], but fails instead of panicking
+pub fn try_encrypt<C: BlockEncrypt>(cipher: &C, msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_aligned(msg.len(), block_len)?;
+
+    let mut result = msg.to_vec();
+    for chunk in result.chunks_exact_mut(block_len) {
+        cipher.encrypt_block(chunk.into());
+    }
+    Ok(result)
+}
+
+/// does not strip padding. Panics if `msg` isn't block-aligned.
+pub fn decrypt<C: BlockDecrypt>(cipher: &C, msg: &[u8]) -> Vec<u8> {
+    try_decrypt(cipher, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt<C: BlockDecrypt>(cipher: &C, msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_aligned(msg.len(), block_len)?;
+
+    let mut result = msg.to_vec();
+    for chunk in result.chunks_exact_mut(block_len) {
+        cipher.decrypt_block(chunk.into());
+    }
+    Ok(result)
+}
+
+pub fn encrypt_padded<C: BlockEncrypt>(
+    cipher: &C,
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
+    let mut padded = msg.to_vec();
+    padding.pad(&mut padded, block_len::<C>())?;
+    Ok(encrypt(cipher, &padded))
+}
+
+pub fn decrypt_padded<C: BlockDecrypt>(
+    cipher: &C,
+    msg: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, PaddingError> {
+    let block_len = block_len::<C>();
+    if !msg.len().is_multiple_of(block_len) {
+        return Err(PaddingError::NotBlockAligned {
+            len: msg.len(),
+            block_len,
+        });
+    }
+    let mut result = decrypt(cipher, msg);
+    padding.unpad(&mut result, block_len)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::test_vectors::*;
+    use aes::{Aes128, Aes192, Aes256, NewBlockCipher};
+    use hex_literal::hex;
+
+    #[test]
+    fn test_aes128() {
+        let expected = hex!("3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4");
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &PLAINTEXT), expected);
+        assert_eq!(decrypt(&cipher, &expected), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_padded_round_trip() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        for &padding in Padding::ALL.iter() {
+            for len in (0..=48).filter(|&len| padding != Padding::None || len % 16 == 0) {
+                let ciphertext = encrypt_padded(&cipher, &PLAINTEXT[..len], padding).unwrap();
+                assert_eq!(
+                    decrypt_padded(&cipher, &ciphertext, padding),
+                    Ok(PLAINTEXT[..len].to_vec())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_aes192() {
+        let expected = hex!("bd334f1d6e45f25ff712a214571fa5cc974104846d0ad3ad7734ecb3ecee4eefef7afd2270e2e60adce0ba2face6444e9a4b41ba738d6c72fb16691603c18e0e");
+        let cipher = Aes192::new(&AES192_KEY.into());
+        assert_eq!(encrypt(&cipher, &PLAINTEXT), expected);
+        assert_eq!(decrypt(&cipher, &expected), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_aes256() {
+        let expected = hex!("f3eed1bdb5d2a03c064b5a7e3db181f8591ccb10d410ed26dc5ba74a31362870b6ed21b99ca6f4f9f153e7b1beafed1d23304b7a39f9f3ff067d8d8f9e24ecc7");
+        let cipher = Aes256::new(&AES256_KEY.into());
+        assert_eq!(encrypt(&cipher, &PLAINTEXT), expected);
+        assert_eq!(decrypt(&cipher, &expected), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_try_not_aligned() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert!(matches!(
+            try_encrypt(&cipher, &PLAINTEXT[..17]),
+            Err(Error::NotBlockAligned {
+                len: 17,
+                block_len: 16
+            })
+        ));
+        assert!(matches!(
+            try_decrypt(&cipher, &PLAINTEXT[..15]),
+            Err(Error::NotBlockAligned { .. })
+        ));
+        assert_eq!(try_encrypt(&cipher, &[]).unwrap(), b"");
+    }
+}
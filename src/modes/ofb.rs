@@ -0,0 +1,58 @@
+//! Output feedback mode. Encryption and decryption are the same operation.
+
+use super::{block_len, xor_in_place};
+use crate::error::check_iv;
+use crate::Error;
+use aes::BlockEncrypt;
+
+/// Panics if `iv` isn't one block long
+pub fn encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    try_encrypt(cipher, iv, msg).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like [`encrypt`], but fails instead of panicking
+pub fn try_encrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    let block_len = block_len::<C>();
+    check_iv(iv, block_len)?;
+
+    let mut result = msg.to_vec();
+    let mut keystream = iv.to_vec();
+    for chunk in result.chunks_mut(block_len) {
+        cipher.encrypt_block(keystream.as_mut_slice().into());
+        xor_in_place(chunk, &keystream[..chunk.len()]);
+    }
+    Ok(result)
+}
+
+/// Panics if `iv` isn't one block long
+pub fn decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Vec<u8> {
+    encrypt(cipher, iv, msg)
+}
+
+/// Like [`decrypt`], but fails instead of panicking
+pub fn try_decrypt<C: BlockEncrypt>(cipher: &C, iv: &[u8], msg: &[u8]) -> Result<Vec<u8>, Error> {
+    try_encrypt(cipher, iv, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::test_vectors::*;
+    use aes::{Aes128, NewBlockCipher};
+    use hex_literal::hex;
+
+    const EXPECTED: [u8; 64] = hex!("3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed8259740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e");
+
+    #[test]
+    fn test_aes128() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT), EXPECTED);
+        assert_eq!(decrypt(&cipher, &IV, &EXPECTED), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_partial_block() {
+        let cipher = Aes128::new(&AES128_KEY.into());
+        assert_eq!(encrypt(&cipher, &IV, &PLAINTEXT[..20]), &EXPECTED[..20]);
+    }
+}
@@ -0,0 +1,383 @@
+//! The Mersenne Twister, in its usual 32-bit form and the 64-bit MT19937-64.
+//!
+//! Each output is one word of the internal state put through an invertible "tempering" step, and
+//! the state is regenerated ("twisted") every `N` outputs from the previous state alone. So
+//! [`untemper`] turns an output back into a state word, and `N` consecutive outputs give away
+//! the whole state; see [`crate::attacks::mt_clone`]. Not for anything that needs to be
+//! unpredictable.
+
+use rand::RngCore;
+
+/// Words of state, and outputs between twists, of [`Mt19937`]
+pub const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+/// Words of state, and outputs between twists, of [`Mt19937_64`]
+pub const N_64: usize = 312;
+const M_64: usize = 156;
+const MATRIX_A_64: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK_64: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK_64: u64 = 0x7fff_ffff;
+
+/// The seed both reference implementations use when none is given
+pub const DEFAULT_SEED: u32 = 5489;
+
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; N],
+    /// the next state word to output; `N` means a twist is due
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Self {
+        let mut state = [0u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            let prev = state[i - 1];
+            state[i] = 1_812_433_253u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: N }
+    }
+
+    /// Seeds from an array of any length, like `init_by_array` in the reference code
+    pub fn from_key(key: &[u32]) -> Self {
+        let mut mt = Mt19937::new(19_650_218);
+        let state = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N.max(key.len()) {
+            let prev = state[i - 1];
+            state[i] = (state[i] ^ (prev ^ (prev >> 30)).wrapping_mul(1_664_525))
+                .wrapping_add(key.get(j).copied().unwrap_or(0))
+                .wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= N {
+                state[0] = state[N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N - 1 {
+            let prev = state[i - 1];
+            state[i] = (state[i] ^ (prev ^ (prev >> 30)).wrapping_mul(1_566_083_941))
+                .wrapping_sub(i as u32);
+            i += 1;
+            if i >= N {
+                state[0] = state[N - 1];
+                i = 1;
+            }
+        }
+        // guarantees a non-zero state
+        state[0] = UPPER_MASK;
+        mt
+    }
+
+    /// A generator that has just output (the tempered form of) all of `state`
+    pub fn from_state(state: [u32; N]) -> Self {
+        Mt19937 { state, index: N }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper(y)
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 1 { MATRIX_A } else { 0 };
+            self.state[i] = self.state[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl Default for Mt19937 {
+    fn default() -> Self {
+        Mt19937::new(DEFAULT_SEED)
+    }
+}
+
+impl RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937::next_u32(self)
+    }
+
+    /// Two outputs, the first in the low half
+    fn next_u64(&mut self) -> u64 {
+        let low = Mt19937::next_u32(self) as u64;
+        low | ((Mt19937::next_u32(self) as u64) << 32)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = Mt19937::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^ (y >> 18)
+}
+
+/// Inverts [`temper`], giving back the state word behind an output
+pub fn untemper(mut y: u32) -> u32 {
+    y = undo_right(y as u64, 18, u32::MAX as u64, 32) as u32;
+    y = undo_left(y as u64, 15, 0xefc6_0000, 32) as u32;
+    y = undo_left(y as u64, 7, 0x9d2c_5680, 32) as u32;
+    undo_right(y as u64, 11, u32::MAX as u64, 32) as u32
+}
+
+#[derive(Clone)]
+pub struct Mt19937_64 {
+    state: [u64; N_64],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    pub fn new(seed: u64) -> Self {
+        let mut state = [0u64; N_64];
+        state[0] = seed;
+        for i in 1..N_64 {
+            let prev = state[i - 1];
+            state[i] = 6_364_136_223_846_793_005u64
+                .wrapping_mul(prev ^ (prev >> 62))
+                .wrapping_add(i as u64);
+        }
+        Mt19937_64 { state, index: N_64 }
+    }
+
+    /// Seeds from an array of any length, like `init_by_array64` in the reference code
+    pub fn from_key(key: &[u64]) -> Self {
+        let mut mt = Mt19937_64::new(19_650_218);
+        let state = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..N_64.max(key.len()) {
+            let prev = state[i - 1];
+            state[i] = (state[i] ^ (prev ^ (prev >> 62)).wrapping_mul(3_935_559_000_370_003_845))
+                .wrapping_add(key.get(j).copied().unwrap_or(0))
+                .wrapping_add(j as u64);
+            i += 1;
+            j += 1;
+            if i >= N_64 {
+                state[0] = state[N_64 - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..N_64 - 1 {
+            let prev = state[i - 1];
+            state[i] = (state[i] ^ (prev ^ (prev >> 62)).wrapping_mul(2_862_933_555_777_941_757))
+                .wrapping_sub(i as u64);
+            i += 1;
+            if i >= N_64 {
+                state[0] = state[N_64 - 1];
+                i = 1;
+            }
+        }
+        state[0] = 1 << 63;
+        mt
+    }
+
+    /// A generator that has just output (the tempered form of) all of `state`
+    pub fn from_state(state: [u64; N_64]) -> Self {
+        Mt19937_64 { state, index: N_64 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= N_64 {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper_64(y)
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N_64 {
+            let y = (self.state[i] & UPPER_MASK_64) | (self.state[(i + 1) % N_64] & LOWER_MASK_64);
+            let mag = if y & 1 == 1 { MATRIX_A_64 } else { 0 };
+            self.state[i] = self.state[(i + M_64) % N_64] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+}
+
+impl Default for Mt19937_64 {
+    fn default() -> Self {
+        Mt19937_64::new(DEFAULT_SEED as u64)
+    }
+}
+
+impl RngCore for Mt19937_64 {
+    /// The low half of an output
+    fn next_u32(&mut self) -> u32 {
+        Mt19937_64::next_u64(self) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Mt19937_64::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = Mt19937_64::next_u64(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+pub fn temper_64(mut y: u64) -> u64 {
+    y ^= (y >> 29) & 0x5555_5555_5555_5555;
+    y ^= (y << 17) & 0x71d6_7fff_eda6_0000;
+    y ^= (y << 37) & 0xfff7_eee0_0000_0000;
+    y ^ (y >> 43)
+}
+
+/// Inverts [`temper_64`]
+pub fn untemper_64(mut y: u64) -> u64 {
+    y = undo_right(y, 43, u64::MAX, 64);
+    y = undo_left(y, 37, 0xfff7_eee0_0000_0000, 64);
+    y = undo_left(y, 17, 0x71d6_7fff_eda6_0000, 64);
+    undo_right(y, 29, 0x5555_5555_5555_5555, 64)
+}
+
+/// Inverts `y ^= (y >> shift) & mask` on a `bits` wide word. Each pass fixes another `shift`
+/// bits, starting from the top ones, which the step left alone.
+fn undo_right(y: u64, shift: u32, mask: u64, bits: u32) -> u64 {
+    let mut x = y;
+    for _ in 0..bits.div_ceil(shift) {
+        x = y ^ ((x >> shift) & mask);
+    }
+    x
+}
+
+/// Inverts `y ^= (y << shift) & mask` on a `bits` wide word, fixing bits from the bottom up
+fn undo_left(y: u64, shift: u32, mask: u64, bits: u32) -> u64 {
+    let word = if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    let mut x = y;
+    for _ in 0..bits.div_ceil(shift) {
+        x = y ^ ((x << shift) & mask & word);
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_reference_outputs() {
+        // mt19937ar.c and the C++ standard library
+        let mut mt = Mt19937::default();
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(
+            first,
+            [3499211612, 581869302, 3890346734, 3586334585, 545404204]
+        );
+        let mut mt = Mt19937::default();
+        assert_eq!((0..10000).map(|_| mt.next_u32()).last(), Some(4123659995));
+
+        let mut mt = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(
+            first,
+            [1067595299, 955945823, 477289528, 4107218783, 4228976476]
+        );
+    }
+
+    #[test]
+    fn test_reference_outputs_64() {
+        // mt19937-64.c and the C++ standard library
+        let mut mt = Mt19937_64::default();
+        assert_eq!(mt.next_u64(), 14514284786278117030);
+        let mut mt = Mt19937_64::default();
+        assert_eq!(
+            (0..10000).map(|_| mt.next_u64()).last(),
+            Some(9981545732273789042)
+        );
+
+        let mut mt = Mt19937_64::from_key(&[0x12345, 0x23456, 0x34567, 0x45678]);
+        let first: Vec<u64> = (0..3).map(|_| mt.next_u64()).collect();
+        assert_eq!(
+            first,
+            [
+                7266447313870364031,
+                4946485549665804864,
+                16945909448695747420
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untemper() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for y in [0, 1, u32::MAX, 0x8000_0000]
+            .iter()
+            .copied()
+            .chain((0..10000).map(|_| rng.gen()))
+        {
+            assert_eq!(untemper(temper(y)), y);
+            assert_eq!(temper(untemper(y)), y);
+        }
+        for y in [0, 1, u64::MAX, 1 << 63]
+            .iter()
+            .copied()
+            .chain((0..10000).map(|_| rng.gen()))
+        {
+            assert_eq!(untemper_64(temper_64(y)), y);
+            assert_eq!(temper_64(untemper_64(y)), y);
+        }
+    }
+
+    #[test]
+    fn test_rng_core() {
+        let mut mt = Mt19937::new(1);
+        let mut reference = Mt19937::new(1);
+        let mut bytes = [0u8; 6];
+        mt.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..4], reference.next_u32().to_le_bytes());
+        assert_eq!(bytes[4..], reference.next_u32().to_le_bytes()[..2]);
+        // usable wherever the rand crate wants a generator
+        let roll = mt.gen_range(1..=6);
+        assert!((1..=6).contains(&roll));
+
+        let mut mt = Mt19937_64::new(1);
+        let mut reference = Mt19937_64::new(1);
+        assert_eq!(RngCore::next_u32(&mut mt), reference.next_u64() as u32);
+    }
+}
@@ -0,0 +1,340 @@
+//! Character n-gram language models. These are trained from a corpus by the `charfreq` binary
+//! and saved in a small versioned text format, so training only has to happen once.
+//!
+//! The format is line based:
+//!
+//! ```text
+//! cryptopals-ngram 1
+//! n 3
+//! add-k 0.5
+//! 205448 12
+//! ...
+//! ```
+//!
+//! followed by one line per n-gram holding the n-gram as hex and its count.
+
+use crate::scoring::Scorer;
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::str::FromStr;
+
+pub const FORMAT_VERSION: u32 = 1;
+const MAGIC: &str = "cryptopals-ngram";
+/// Symbols are ASCII bytes
+const ALPHABET_LEN: f64 = 128.0;
+
+#[derive(Debug)]
+pub enum NgramError {
+    IoError(io::Error),
+    UnsupportedVersion(u32),
+    InvalidFormat(&'static str),
+    /// `n` or the smoothing can't make a usable model
+    InvalidParameter(&'static str),
+}
+
+impl Display for NgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NgramError::IoError(e) => write!(f, "IO error: {}", e),
+            NgramError::UnsupportedVersion(v) => write!(f, "unsupported format version: {}", v),
+            NgramError::InvalidFormat(e) => write!(f, "invalid n-gram file: {}", e),
+            NgramError::InvalidParameter(e) => write!(f, "invalid n-gram model: {}", e),
+        }
+    }
+}
+
+impl error::Error for NgramError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            NgramError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Smoothing {
+    /// add `k` to every count, including n-grams that were never seen
+    AddK(f64),
+}
+
+impl Smoothing {
+    pub const LAPLACE: Smoothing = Smoothing::AddK(1.0);
+}
+
+/// Why `n` and `smoothing` can't make a usable model, if they can't
+fn check_params(n: usize, smoothing: Smoothing) -> Result<(), &'static str> {
+    if n == 0 {
+        return Err("invalid n");
+    }
+    let Smoothing::AddK(k) = smoothing;
+    // anything else makes unseen n-grams impossible, or every probability NaN
+    if !k.is_finite() || k <= 0.0 {
+        return Err("invalid smoothing");
+    }
+    Ok(())
+}
+
+/// Maps text onto the alphabet the models are trained on: ASCII, upper case, with typographic
+/// dashes and quotes replaced by their plain equivalents. Carriage returns and anything else
+/// outside ASCII are dropped.
+pub fn normalize(text: &str) -> Vec<u8> {
+    text.chars()
+        .flat_map(char::to_uppercase)
+        .filter_map(|chr| match chr {
+            '\u{2013}' | '\u{2014}' => Some(b'-'),
+            '\u{2018}' | '\u{2019}' => Some(b'\''),
+            '\u{201c}' | '\u{201d}' => Some(b'"'),
+            '\r' => None,
+            c if c > '~' => None,
+            c => Some(c as u8),
+        })
+        .collect()
+}
+
+/// Predicts each character from the `n - 1` before it
+pub struct NgramModel {
+    n: usize,
+    smoothing: Smoothing,
+    counts: HashMap<Vec<u8>, u64>,
+    /// how often each (n-1)-gram starts an n-gram
+    context_counts: HashMap<Vec<u8>, u64>,
+}
+
+impl NgramModel {
+    /// `text` should already be passed through [`normalize`]. Panics if `n` is 0 or the
+    /// smoothing `k` isn't positive and finite.
+    pub fn train(text: &[u8], n: usize, smoothing: Smoothing) -> NgramModel {
+        NgramModel::try_train(text, n, smoothing).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`NgramModel::train`], but fails instead of panicking
+    pub fn try_train(
+        text: &[u8],
+        n: usize,
+        smoothing: Smoothing,
+    ) -> Result<NgramModel, NgramError> {
+        check_params(n, smoothing).map_err(NgramError::InvalidParameter)?;
+        let mut counts = HashMap::new();
+        for gram in text.windows(n) {
+            *counts.entry(gram.to_vec()).or_insert(0) += 1;
+        }
+        Ok(NgramModel::from_counts(n, smoothing, counts))
+    }
+
+    fn from_counts(n: usize, smoothing: Smoothing, counts: HashMap<Vec<u8>, u64>) -> NgramModel {
+        let mut context_counts = HashMap::new();
+        for (gram, count) in counts.iter() {
+            *context_counts.entry(gram[..n - 1].to_vec()).or_insert(0) += count;
+        }
+        NgramModel {
+            n,
+            smoothing,
+            counts,
+            context_counts,
+        }
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    /// log10 of the probability of the last byte of `gram` following the rest of it. Panics if
+    /// `gram` isn't `n` bytes long.
+    pub fn log_prob(&self, gram: &[u8]) -> f64 {
+        assert_eq!(gram.len(), self.n);
+        let Smoothing::AddK(k) = self.smoothing;
+        let count = *self.counts.get(gram).unwrap_or(&0) as f64;
+        let context = *self.context_counts.get(&gram[..self.n - 1]).unwrap_or(&0) as f64;
+        ((count + k) / (context + k * ALPHABET_LEN)).log10()
+    }
+
+    /// log10 probability of everything in `text` after the first `n - 1` bytes. Case is ignored.
+    pub fn text_log_prob(&self, text: &[u8]) -> f64 {
+        text.to_ascii_uppercase()
+            .windows(self.n)
+            .map(|gram| self.log_prob(gram))
+            .sum()
+    }
+
+    pub fn write_to<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let Smoothing::AddK(k) = self.smoothing;
+        writeln!(w, "{} {}", MAGIC, FORMAT_VERSION)?;
+        writeln!(w, "n {}", self.n)?;
+        writeln!(w, "add-k {}", k)?;
+        let mut grams: Vec<(&Vec<u8>, &u64)> = self.counts.iter().collect();
+        grams.sort_unstable();
+        for (gram, count) in grams {
+            writeln!(w, "{} {}", hex::encode(gram), count)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: io::BufRead>(r: R) -> Result<NgramModel, NgramError> {
+        let mut lines = r.lines();
+
+        let version: u32 = header_value(lines.next(), MAGIC, "invalid version")?;
+        if version != FORMAT_VERSION {
+            return Err(NgramError::UnsupportedVersion(version));
+        }
+        let n: usize = header_value(lines.next(), "n", "invalid n")?;
+        let k: f64 = header_value(lines.next(), "add-k", "invalid smoothing")?;
+        check_params(n, Smoothing::AddK(k)).map_err(NgramError::InvalidFormat)?;
+
+        let mut counts = HashMap::new();
+        for line in lines {
+            let line = line.map_err(NgramError::IoError)?;
+            let mut parts = line.split(' ');
+            let gram = parts
+                .next()
+                .and_then(|g| hex::decode(g).ok())
+                .filter(|g| g.len() == n)
+                .ok_or(NgramError::InvalidFormat("invalid n-gram"))?;
+            let count: u64 = parts
+                .next()
+                .and_then(|c| c.parse().ok())
+                .ok_or(NgramError::InvalidFormat("invalid count"))?;
+            if parts.next().is_some() {
+                return Err(NgramError::InvalidFormat("too many columns"));
+            }
+            counts.insert(gram, count);
+        }
+        Ok(NgramModel::from_counts(n, Smoothing::AddK(k), counts))
+    }
+}
+
+/// Parses a `key value` header line
+fn header_value<T: FromStr>(
+    line: Option<io::Result<String>>,
+    key: &str,
+    invalid: &'static str,
+) -> Result<T, NgramError> {
+    let line = line
+        .ok_or(NgramError::InvalidFormat("truncated header"))?
+        .map_err(NgramError::IoError)?;
+    let mut parts = line.splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(found), Some(value)) if found == key => value
+            .parse()
+            .map_err(|_| NgramError::InvalidFormat(invalid)),
+        _ => Err(NgramError::InvalidFormat("invalid header")),
+    }
+}
+
+/// Average log10 probability per predicted character. Bytes outside ASCII never occur in the
+/// model, so they get the smoothed probability of an unseen n-gram.
+impl Scorer for NgramModel {
+    fn score(&self, plaintext: &[u8]) -> f64 {
+        let predicted = plaintext.len().saturating_sub(self.n - 1);
+        if predicted == 0 {
+            return (1.0 / ALPHABET_LEN).log10();
+        }
+        self.text_log_prob(plaintext) / predicted as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn moby_dick() -> Vec<u8> {
+        let text =
+            fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/MobyDick.txt")).unwrap();
+        normalize(&text)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("Call me \u{201c}Ishmael\u{201d}\u{2014}caf\u{e9}\r\n"),
+            b"CALL ME \"ISHMAEL\"-CAF\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let model = NgramModel::train(b"THE CAT SAT ON THE MAT", 3, Smoothing::AddK(0.5));
+        let mut file = Vec::new();
+        model.write_to(&mut file).unwrap();
+        assert!(file.starts_with(b"cryptopals-ngram 1\nn 3\nadd-k 0.5\n"));
+
+        let loaded = NgramModel::read_from(&file[..]).unwrap();
+        assert_eq!(loaded.n(), 3);
+        assert_eq!(loaded.smoothing(), Smoothing::AddK(0.5));
+        assert_eq!(loaded.counts, model.counts);
+        assert_eq!(loaded.context_counts, model.context_counts);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(matches!(
+            NgramModel::read_from(&b"cryptopals-ngram 2\nn 3\nadd-k 1\n"[..]),
+            Err(NgramError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            NgramModel::read_from(&b"cryptopals-ngram 1\nn 3\n"[..]),
+            Err(NgramError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            NgramModel::read_from(&b"cryptopals-ngram 1\nn 2\nadd-k 1\n414243 5\n"[..]),
+            Err(NgramError::InvalidFormat("invalid n-gram"))
+        ));
+        for k in ["0", "-1", "NaN", "inf"].iter() {
+            let file = format!("cryptopals-ngram 1\nn 2\nadd-k {}\n", k);
+            assert!(
+                matches!(
+                    NgramModel::read_from(file.as_bytes()),
+                    Err(NgramError::InvalidFormat("invalid smoothing"))
+                ),
+                "{}",
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn test_train_errors() {
+        assert!(matches!(
+            NgramModel::try_train(b"THE CAT", 0, Smoothing::LAPLACE),
+            Err(NgramError::InvalidParameter("invalid n"))
+        ));
+        for &k in [0.0, -1.0, f64::NAN, f64::INFINITY].iter() {
+            assert!(
+                matches!(
+                    NgramModel::try_train(b"THE CAT", 2, Smoothing::AddK(k)),
+                    Err(NgramError::InvalidParameter("invalid smoothing"))
+                ),
+                "{}",
+                k
+            );
+        }
+        assert!(NgramModel::try_train(b"THE CAT", 2, Smoothing::LAPLACE).is_ok());
+    }
+
+    #[test]
+    fn test_probabilities_sum_to_one() {
+        let model = NgramModel::train(b"ABABABAC", 2, Smoothing::LAPLACE);
+        let total: f64 = (0..128u8)
+            .map(|b| 10f64.powf(model.log_prob(&[b'A', b])))
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scores_english_higher() {
+        let corpus = moby_dick();
+        for n in 2..=3 {
+            let model = NgramModel::train(&corpus, n, Smoothing::AddK(0.5));
+            let english = model.score(b"the whale");
+            let shifted = model.score(b"uif xibmf");
+            assert!(english > shifted, "n = {}", n);
+        }
+    }
+}
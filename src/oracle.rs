@@ -0,0 +1,392 @@
+//! Cryptopals-style encryption oracles, for testing attacks against unknown keys.
+
+use crate::attacks::padding_oracle::PaddingOracle;
+use crate::ct;
+use crate::detect_ecb;
+use crate::hash::HashFunction;
+use crate::mac::{insecure_compare, Hmac, MacVerifier, SecretPrefixMac};
+use crate::modes::{cbc, ecb};
+use crate::padding::Padding;
+use crate::PaddingError;
+use aes::{Aes128, NewBlockCipher};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+
+const BLOCK_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+}
+
+impl Mode {
+    /// Guesses the mode that produced `ciphertext` by looking for repeated blocks. Only works if
+    /// the plaintext contained repeated aligned blocks.
+    pub fn detect(ciphertext: &[u8], block_len: usize) -> Mode {
+        if detect_ecb(ciphertext, block_len) {
+            Mode::Ecb
+        } else {
+            Mode::Cbc
+        }
+    }
+}
+
+/// Whether a server checks secrets the quick, leaky way or in constant time with [`ct`], so the
+/// same attack can be run against both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Hardening {
+    #[default]
+    Vulnerable,
+    Hardened,
+}
+
+/// Encrypts `random prefix || input || random suffix` under AES-128 with a fresh random key,
+/// picking ECB or CBC (random IV) with equal probability on every call. The prefix and suffix
+/// are each 5-10 random bytes.
+pub struct EncryptionOracle {
+    rng: StdRng,
+    last_mode: Option<Mode>,
+}
+
+impl EncryptionOracle {
+    pub fn new() -> Self {
+        EncryptionOracle {
+            rng: StdRng::from_entropy(),
+            last_mode: None,
+        }
+    }
+
+    /// Reproducible oracle, for tests
+    pub fn with_seed(seed: u64) -> Self {
+        EncryptionOracle {
+            rng: StdRng::seed_from_u64(seed),
+            last_mode: None,
+        }
+    }
+
+    /// The mode used by the most recent call to `encrypt`
+    pub fn last_mode(&self) -> Option<Mode> {
+        self.last_mode
+    }
+
+    pub fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let key: [u8; BLOCK_LEN] = self.rng.gen();
+        let cipher = Aes128::new(&key.into());
+
+        let prefix_len = self.rng.gen_range(5..=10);
+        let suffix_len = self.rng.gen_range(5..=10);
+        let mut msg: Vec<u8> = (0..prefix_len).map(|_| self.rng.gen()).collect();
+        msg.extend_from_slice(input);
+        msg.extend((0..suffix_len).map(|_| self.rng.gen::<u8>()));
+
+        let mode = if self.rng.gen() { Mode::Ecb } else { Mode::Cbc };
+        self.last_mode = Some(mode);
+        let ciphertext = match mode {
+            Mode::Ecb => ecb::encrypt_padded(&cipher, &msg, Padding::Pkcs7),
+            Mode::Cbc => {
+                let iv: [u8; BLOCK_LEN] = self.rng.gen();
+                cbc::encrypt_padded(&cipher, &iv, &msg, Padding::Pkcs7)
+            }
+        };
+        ciphertext.expect("PKCS#7 padding cannot fail")
+    }
+
+    /// Runs `trials` encryptions of `input` and returns the fraction for which `guess` picked
+    /// the mode that was actually used; 0 for no trials
+    pub fn detection_accuracy<F>(&mut self, trials: usize, input: &[u8], mut guess: F) -> f64
+    where
+        F: FnMut(&[u8]) -> Mode,
+    {
+        if trials == 0 {
+            return 0.0;
+        }
+        let correct = (0..trials)
+            .filter(|_| {
+                let ciphertext = self.encrypt(input);
+                Some(guess(&ciphertext)) == self.last_mode
+            })
+            .count();
+        correct as f64 / trials as f64
+    }
+}
+
+impl Default for EncryptionOracle {
+    fn default() -> Self {
+        EncryptionOracle::new()
+    }
+}
+
+/// Stands in for a server that accepts AES-128-CBC ciphertext under a key it keeps to itself and
+/// reveals whether the PKCS#7 padding was valid. Hardening only makes the check constant time;
+/// the answer itself still gives the padding away.
+pub struct CbcPaddingOracle {
+    cipher: Aes128,
+    rng: StdRng,
+    hardening: Hardening,
+}
+
+impl CbcPaddingOracle {
+    pub fn new() -> Self {
+        CbcPaddingOracle::from_rng(StdRng::from_entropy())
+    }
+
+    /// Reproducible oracle, for tests
+    pub fn with_seed(seed: u64) -> Self {
+        CbcPaddingOracle::from_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn from_rng(mut rng: StdRng) -> Self {
+        let key: [u8; BLOCK_LEN] = rng.gen();
+        CbcPaddingOracle {
+            cipher: Aes128::new(&key.into()),
+            rng,
+            hardening: Hardening::default(),
+        }
+    }
+
+    pub fn hardening(&self) -> Hardening {
+        self.hardening
+    }
+
+    pub fn set_hardening(&mut self, hardening: Hardening) {
+        self.hardening = hardening;
+    }
+
+    /// Returns a random IV and the padded ciphertext
+    pub fn encrypt(&mut self, msg: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let iv: [u8; BLOCK_LEN] = self.rng.gen();
+        let ciphertext = cbc::encrypt_padded(&self.cipher, &iv, msg, Padding::Pkcs7)
+            .expect("PKCS#7 padding cannot fail");
+        (iv.to_vec(), ciphertext)
+    }
+
+    pub fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        if iv.len() != BLOCK_LEN {
+            return Err(PaddingError::BadIvLength {
+                expected: BLOCK_LEN,
+                actual: iv.len(),
+            });
+        }
+        match self.hardening {
+            Hardening::Vulnerable => cbc::decrypt_and_unpad(&self.cipher, iv, ciphertext),
+            Hardening::Hardened => {
+                if !ciphertext.len().is_multiple_of(BLOCK_LEN) {
+                    return Err(PaddingError::NotBlockAligned {
+                        len: ciphertext.len(),
+                        block_len: BLOCK_LEN,
+                    });
+                }
+                let mut plaintext = cbc::decrypt(&self.cipher, iv, ciphertext);
+                ct::pkcs7_unpad(&mut plaintext, BLOCK_LEN)?;
+                Ok(plaintext)
+            }
+        }
+    }
+}
+
+impl Default for CbcPaddingOracle {
+    fn default() -> Self {
+        CbcPaddingOracle::new()
+    }
+}
+
+impl PaddingOracle for CbcPaddingOracle {
+    fn is_valid(&self, iv: &[u8], ct: &[u8]) -> bool {
+        self.decrypt(iv, ct).is_ok()
+    }
+}
+
+/// Stands in for a server that hands out and checks secret-prefix MACs under a random key of
+/// 1 to 64 bytes
+pub struct SecretPrefixOracle<H> {
+    mac: SecretPrefixMac<H>,
+}
+
+impl<H: HashFunction> SecretPrefixOracle<H> {
+    pub fn new() -> Self {
+        SecretPrefixOracle::from_rng(StdRng::from_entropy())
+    }
+
+    /// Reproducible oracle, for tests
+    pub fn with_seed(seed: u64) -> Self {
+        SecretPrefixOracle::from_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn from_rng(mut rng: StdRng) -> Self {
+        let key_len = rng.gen_range(1..=64);
+        let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+        SecretPrefixOracle {
+            mac: SecretPrefixMac::new(&key),
+        }
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        self.mac.sign(msg)
+    }
+
+    pub fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        self.mac.verify(msg, mac)
+    }
+}
+
+impl<H: HashFunction> Default for SecretPrefixOracle<H> {
+    fn default() -> Self {
+        SecretPrefixOracle::new()
+    }
+}
+
+impl<H: HashFunction> MacVerifier for SecretPrefixOracle<H> {
+    fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        self.mac.verify(msg, mac)
+    }
+}
+
+/// Stands in for a server that checks HMACs under a random 16 byte key with
+/// [`insecure_compare`], so every correct leading byte of a MAC makes it `delay` slower to say no.
+/// Hardened, it uses [`ct::eq`] instead and `delay` is ignored.
+pub struct HmacOracle<H> {
+    hmac: Hmac<H>,
+    delay: Duration,
+    hardening: Hardening,
+}
+
+impl<H: HashFunction> HmacOracle<H> {
+    pub fn new(delay: Duration) -> Self {
+        HmacOracle::from_rng(StdRng::from_entropy(), delay)
+    }
+
+    /// Reproducible oracle, for tests
+    pub fn with_seed(seed: u64, delay: Duration) -> Self {
+        HmacOracle::from_rng(StdRng::seed_from_u64(seed), delay)
+    }
+
+    fn from_rng(mut rng: StdRng, delay: Duration) -> Self {
+        let key: [u8; 16] = rng.gen();
+        HmacOracle {
+            hmac: Hmac::new(&key),
+            delay,
+            hardening: Hardening::default(),
+        }
+    }
+
+    pub fn hardening(&self) -> Hardening {
+        self.hardening
+    }
+
+    pub fn set_hardening(&mut self, hardening: Hardening) {
+        self.hardening = hardening;
+    }
+
+    /// The MAC the server expects, for checking an attack's answer
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        let mut hmac = self.hmac.clone();
+        hmac.update(msg);
+        hmac.finalize()
+    }
+
+    pub fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        let expected = self.sign(msg);
+        match self.hardening {
+            Hardening::Vulnerable => insecure_compare(mac, &expected, self.delay),
+            Hardening::Hardened => ct::eq(mac, &expected),
+        }
+    }
+}
+
+impl<H: HashFunction> MacVerifier for HmacOracle<H> {
+    fn verify(&self, msg: &[u8], mac: &[u8]) -> bool {
+        HmacOracle::verify(self, msg, mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_shape() {
+        let mut oracle = EncryptionOracle::with_seed(1);
+        for len in 0..64 {
+            let ciphertext = oracle.encrypt(&vec![0; len]);
+            assert_eq!(ciphertext.len() % BLOCK_LEN, 0);
+            assert!(ciphertext.len() >= len + 10);
+            assert!(ciphertext.len() <= len + 20 + BLOCK_LEN);
+        }
+    }
+
+    #[test]
+    fn test_detection_accuracy() {
+        let mut oracle = EncryptionOracle::with_seed(11);
+        // at most 11 bytes of the input complete the prefix's block, leaving two aligned blocks
+        let input = [b'A'; 2 * BLOCK_LEN + 11];
+        let accuracy = oracle.detection_accuracy(2000, &input, |ct| Mode::detect(ct, BLOCK_LEN));
+        assert_eq!(accuracy, 1.0);
+        assert_eq!(oracle.detection_accuracy(0, &input, |_| Mode::Ecb), 0.0);
+    }
+
+    #[test]
+    fn test_bad_iv_length() {
+        // a malformed request, not a padding verdict
+        let mut server = CbcPaddingOracle::with_seed(15);
+        let (iv, ciphertext) = server.encrypt(b"YELLOW SUBMARINE");
+        for iv in [&iv[..0], &iv[..15], &[0; 17][..]].iter() {
+            assert_eq!(
+                server.decrypt(iv, &ciphertext),
+                Err(PaddingError::BadIvLength {
+                    expected: BLOCK_LEN,
+                    actual: iv.len()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_hardened_padding_oracle() {
+        // same answers either way, including for ciphertexts the attack crafts
+        let mut server = CbcPaddingOracle::with_seed(13);
+        let (iv, ciphertext) = server.encrypt(b"YELLOW SUBMARINE and more");
+        let mut rng = StdRng::seed_from_u64(14);
+        for _ in 0..2000 {
+            let mut iv = iv.clone();
+            iv[rng.gen_range(0..BLOCK_LEN)] ^= rng.gen::<u8>();
+            let mut ct = ciphertext[..BLOCK_LEN].to_vec();
+            ct[rng.gen_range(0..BLOCK_LEN)] ^= rng.gen::<u8>();
+            let vulnerable = server.is_valid(&iv, &ct);
+            server.set_hardening(Hardening::Hardened);
+            assert_eq!(server.is_valid(&iv, &ct), vulnerable);
+            server.set_hardening(Hardening::Vulnerable);
+        }
+
+        server.set_hardening(Hardening::Hardened);
+        assert_eq!(server.hardening(), Hardening::Hardened);
+        assert_eq!(
+            server.decrypt(&iv, &ciphertext),
+            Ok(b"YELLOW SUBMARINE and more".to_vec())
+        );
+        assert_eq!(
+            server.decrypt(&iv, &ciphertext[1..]),
+            Err(PaddingError::NotBlockAligned {
+                len: 31,
+                block_len: BLOCK_LEN
+            })
+        );
+        assert_eq!(
+            server.decrypt(&iv[1..], &ciphertext),
+            Err(PaddingError::BadIvLength {
+                expected: BLOCK_LEN,
+                actual: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_detection_needs_repeats() {
+        // with no repeated input the detector always says CBC, so it's right about half the time
+        let mut oracle = EncryptionOracle::with_seed(12);
+        let input: Vec<u8> = (0..64).collect();
+        let accuracy = oracle.detection_accuracy(2000, &input, |ct| Mode::detect(ct, BLOCK_LEN));
+        assert!(accuracy > 0.4 && accuracy < 0.6, "{}", accuracy);
+    }
+}
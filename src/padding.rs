@@ -0,0 +1,220 @@
+//! Block padding schemes, selectable at runtime so ECB and CBC can share them.
+
+use crate::{pkcs7_unpad, try_pkcs7_pad, PaddingError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// always adds 1 to `block_len` bytes, each equal to the number added
+    Pkcs7,
+    /// always adds zeros followed by a final byte holding the number added
+    AnsiX923,
+    /// ISO/IEC 7816-4: always adds 0x80 followed by zeros
+    Iso7816,
+    /// zeros up to the next block boundary, only if needed. Plaintexts that end in zero bytes
+    /// can't be recovered.
+    Zero,
+    /// no padding; the input must already be block-aligned
+    None,
+}
+
+impl Padding {
+    pub const ALL: [Padding; 5] = [
+        Padding::Pkcs7,
+        Padding::AnsiX923,
+        Padding::Iso7816,
+        Padding::Zero,
+        Padding::None,
+    ];
+
+    /// Fails if `block_len` is 0, or over 255 for the schemes that record the padding length
+    pub fn pad(self, buf: &mut Vec<u8>, block_len: usize) -> Result<(), PaddingError> {
+        let records_len = matches!(self, Padding::Pkcs7 | Padding::AnsiX923);
+        if block_len == 0 || (records_len && block_len > u8::MAX as usize) {
+            return Err(PaddingError::BadBlockLength(block_len));
+        }
+        let to_add = block_len - (buf.len() % block_len);
+        match self {
+            Padding::Pkcs7 => try_pkcs7_pad(buf, block_len)?,
+            Padding::AnsiX923 => {
+                buf.resize(buf.len() + to_add - 1, 0);
+                buf.push(to_add as u8);
+            }
+            Padding::Iso7816 => {
+                buf.push(0x80);
+                buf.resize(buf.len() + to_add - 1, 0);
+            }
+            Padding::Zero => {
+                if to_add != block_len {
+                    buf.resize(buf.len() + to_add, 0);
+                }
+            }
+            Padding::None => {
+                if to_add != block_len {
+                    return Err(PaddingError::NotBlockAligned {
+                        len: buf.len(),
+                        block_len,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Strips padding in place. `buf` is left untouched if the padding is invalid.
+    pub fn unpad(self, buf: &mut Vec<u8>, block_len: usize) -> Result<(), PaddingError> {
+        if self == Padding::Pkcs7 {
+            return pkcs7_unpad(buf, block_len);
+        }
+        if !buf.len().is_multiple_of(block_len) {
+            return Err(PaddingError::NotBlockAligned {
+                len: buf.len(),
+                block_len,
+            });
+        }
+        let last_block = buf.len().saturating_sub(block_len);
+        let new_len = match self {
+            Padding::AnsiX923 => {
+                let pad = *buf.last().ok_or(PaddingError::EmptyInput)?;
+                if pad == 0 || pad as usize > block_len {
+                    return Err(PaddingError::BadPaddingByte(pad));
+                }
+                let new_len = buf.len() - pad as usize;
+                if buf[new_len..buf.len() - 1].iter().any(|&b| b != 0) {
+                    return Err(PaddingError::InconsistentPadding);
+                }
+                new_len
+            }
+            Padding::Iso7816 => {
+                if buf.is_empty() {
+                    return Err(PaddingError::EmptyInput);
+                }
+                match buf[last_block..].iter().rposition(|&b| b != 0) {
+                    Some(i) if buf[last_block + i] == 0x80 => last_block + i,
+                    Some(i) => return Err(PaddingError::BadPaddingByte(buf[last_block + i])),
+                    None => return Err(PaddingError::BadPaddingByte(0)),
+                }
+            }
+            Padding::Zero => buf[last_block..]
+                .iter()
+                .rposition(|&b| b != 0)
+                .map_or(last_block, |i| last_block + i + 1),
+            Padding::None | Padding::Pkcs7 => buf.len(),
+        };
+        buf.truncate(new_len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_LEN: usize = 16;
+
+    /// never contains a zero byte, so zero padding can round-trip it
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 255 + 1) as u8).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &padding in Padding::ALL.iter() {
+            for len in 0..=4 * BLOCK_LEN {
+                let msg = message(len);
+                let mut buf = msg.clone();
+                let result = padding.pad(&mut buf, BLOCK_LEN);
+                if padding == Padding::None && len % BLOCK_LEN != 0 {
+                    assert_eq!(
+                        result,
+                        Err(PaddingError::NotBlockAligned {
+                            len,
+                            block_len: BLOCK_LEN
+                        })
+                    );
+                    continue;
+                }
+                assert_eq!(result, Ok(()), "{:?} len {}", padding, len);
+                assert_eq!(buf.len() % BLOCK_LEN, 0, "{:?} len {}", padding, len);
+                assert_eq!(&buf[..len], &msg[..]);
+                padding.unpad(&mut buf, BLOCK_LEN).unwrap();
+                assert_eq!(buf, msg, "{:?} len {}", padding, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_always_pads() {
+        for &padding in [Padding::Pkcs7, Padding::AnsiX923, Padding::Iso7816].iter() {
+            let mut buf = message(BLOCK_LEN);
+            padding.pad(&mut buf, BLOCK_LEN).unwrap();
+            assert_eq!(buf.len(), 2 * BLOCK_LEN);
+        }
+    }
+
+    #[test]
+    fn test_pad_bytes() {
+        let mut buf = b"YELLOW SUBMARINE".to_vec();
+        Padding::AnsiX923.pad(&mut buf, 20).unwrap();
+        assert_eq!(buf, b"YELLOW SUBMARINE\x00\x00\x00\x04");
+
+        let mut buf = b"YELLOW SUBMARINE".to_vec();
+        Padding::Iso7816.pad(&mut buf, 20).unwrap();
+        assert_eq!(buf, b"YELLOW SUBMARINE\x80\x00\x00\x00");
+
+        let mut buf = b"YELLOW SUBMARINE".to_vec();
+        Padding::Zero.pad(&mut buf, 20).unwrap();
+        assert_eq!(buf, b"YELLOW SUBMARINE\x00\x00\x00\x00");
+    }
+
+    #[test]
+    fn test_unpad_errors() {
+        let mut buf = b"YELLOW SUBMARINE\x00\x01\x00\x04".to_vec();
+        assert_eq!(
+            Padding::AnsiX923.unpad(&mut buf, 20),
+            Err(PaddingError::InconsistentPadding)
+        );
+        let mut buf = b"YELLOW SUBMARINE\x00\x00\x00\x15".to_vec();
+        assert_eq!(
+            Padding::AnsiX923.unpad(&mut buf, 20),
+            Err(PaddingError::BadPaddingByte(0x15))
+        );
+        let mut buf = b"YELLOW SUBMARINE\x81\x00\x00\x00".to_vec();
+        assert_eq!(
+            Padding::Iso7816.unpad(&mut buf, 20),
+            Err(PaddingError::BadPaddingByte(0x81))
+        );
+        let mut buf = vec![0; 20];
+        assert_eq!(
+            Padding::Iso7816.unpad(&mut buf, 20),
+            Err(PaddingError::BadPaddingByte(0))
+        );
+        assert_eq!(buf, vec![0; 20]);
+        let mut buf = b"YELLOW SUBMARINE\x00".to_vec();
+        assert_eq!(
+            Padding::None.unpad(&mut buf, 20),
+            Err(PaddingError::NotBlockAligned {
+                len: 17,
+                block_len: 20
+            })
+        );
+    }
+
+    #[test]
+    fn test_bad_block_length() {
+        for &padding in Padding::ALL.iter() {
+            let mut buf = b"YELLOW".to_vec();
+            assert_eq!(
+                padding.pad(&mut buf, 0),
+                Err(PaddingError::BadBlockLength(0))
+            );
+            assert_eq!(buf, b"YELLOW");
+        }
+        let mut buf = Vec::new();
+        assert_eq!(
+            Padding::AnsiX923.pad(&mut buf, 256),
+            Err(PaddingError::BadBlockLength(256))
+        );
+        assert_eq!(Padding::Iso7816.pad(&mut buf, 256), Ok(()));
+        assert_eq!(buf.len(), 256);
+    }
+}
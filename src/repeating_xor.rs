@@ -0,0 +1,282 @@
+//! Breaking repeating-key XOR without knowing the key length
+
+use crate::analysis::{self, ENGLISH_IOC};
+use crate::scoring::Scorer;
+use crate::{brute_force_single_byte_xor, hamming_distance, repeating_key_xor};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeySizeMetric {
+    /// average Hamming distance between every pair of key-sized blocks, per byte
+    Hamming,
+    /// average index of coincidence of the columns each key byte encrypts
+    IndexOfCoincidence,
+    /// closeness to Friedman's estimate, which is rough but cheap, and poor for text keys
+    Friedman,
+    /// how often the key size is the GCD of two spacings of repeated trigrams
+    Kasiski,
+    /// how often the key size divides the spacing of repeated trigrams
+    KasiskiDivisibility,
+    /// how often bytes match the byte a key size later
+    Autocorrelation,
+}
+
+impl KeySizeMetric {
+    pub const ALL: [KeySizeMetric; 6] = [
+        KeySizeMetric::Hamming,
+        KeySizeMetric::IndexOfCoincidence,
+        KeySizeMetric::Friedman,
+        KeySizeMetric::Kasiski,
+        KeySizeMetric::KasiskiDivisibility,
+        KeySizeMetric::Autocorrelation,
+    ];
+}
+
+#[derive(Clone, Debug)]
+pub struct BreakOptions {
+    pub min_key_len: usize,
+    /// capped at half the ciphertext length, since shorter ciphertexts can't be compared
+    pub max_key_len: usize,
+    /// how many of the best key sizes to fully solve
+    pub candidates: usize,
+    pub metric: KeySizeMetric,
+}
+
+impl Default for BreakOptions {
+    fn default() -> Self {
+        BreakOptions {
+            min_key_len: 2,
+            max_key_len: 40,
+            candidates: 3,
+            metric: KeySizeMetric::Hamming,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeySizeScore {
+    pub key_len: usize,
+    /// higher is more likely. only comparable between scores from the same metric
+    pub score: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub key: Vec<u8>,
+    pub plaintext: Vec<u8>,
+    /// the scorer's score for the whole plaintext
+    pub score: f64,
+    /// the key size score that got this key length tried
+    pub key_size_score: f64,
+}
+
+/// All key sizes in range, most likely first
+pub fn rank_key_sizes(ciphertext: &[u8], options: &BreakOptions) -> Vec<KeySizeScore> {
+    let max_key_len = options.max_key_len.min(ciphertext.len() / 2);
+    let friedman = analysis::friedman(ciphertext, ENGLISH_IOC);
+    let spacings = analysis::kasiski_spacings(ciphertext, 3);
+    let gcds = match options.metric {
+        KeySizeMetric::Kasiski => analysis::spacing_gcds(&spacings),
+        _ => Default::default(),
+    };
+    let mut scores: Vec<KeySizeScore> = (options.min_key_len.max(1)..=max_key_len)
+        .map(|key_len| KeySizeScore {
+            key_len,
+            score: match options.metric {
+                KeySizeMetric::Hamming => -normalized_hamming(ciphertext, key_len),
+                KeySizeMetric::IndexOfCoincidence => {
+                    analysis::column_coincidence(ciphertext, key_len)
+                }
+                KeySizeMetric::Friedman => -(key_len as f64 - friedman).abs(),
+                KeySizeMetric::Kasiski => analysis::kasiski_gcd_score(&gcds, key_len),
+                KeySizeMetric::KasiskiDivisibility => analysis::kasiski_score(&spacings, key_len),
+                KeySizeMetric::Autocorrelation => analysis::autocorrelation(ciphertext, key_len),
+            },
+        })
+        .collect();
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+fn normalized_hamming(ciphertext: &[u8], key_len: usize) -> f64 {
+    let blocks: Vec<&[u8]> = ciphertext.chunks_exact(key_len).collect();
+    let mut total = 0;
+    let mut pairs = 0;
+    for (i, a) in blocks.iter().enumerate() {
+        for b in blocks[i + 1..].iter() {
+            total += hamming_distance(a, b).expect("blocks are the same length");
+            pairs += 1;
+        }
+    }
+    total as f64 / (pairs * key_len) as f64
+}
+
+/// Shortest key that repeats to `key`, so solutions for multiples of the real key length
+/// collapse onto it
+fn minimal_period(key: &[u8]) -> &[u8] {
+    let period = (1..key.len())
+        .find(|&p| {
+            key.len().is_multiple_of(p) && key.iter().zip(key[p..].iter()).all(|(a, b)| a == b)
+        })
+        .unwrap_or(key.len());
+    &key[..period]
+}
+
+/// Solves the `options.candidates` most likely key sizes, treating each column of the
+/// ciphertext as single-byte XOR. Returns distinct solutions, best first.
+pub fn break_repeating_key_xor<S: Scorer + ?Sized>(
+    ciphertext: &[u8],
+    scorer: &S,
+    options: &BreakOptions,
+) -> Vec<Solution> {
+    let mut seen = HashSet::new();
+    let mut solutions: Vec<Solution> = rank_key_sizes(ciphertext, options)
+        .into_iter()
+        .take(options.candidates)
+        .filter_map(|size| {
+            let key: Vec<u8> = (0..size.key_len)
+                .map(|i| {
+                    let column: Vec<u8> = ciphertext[i..]
+                        .iter()
+                        .copied()
+                        .step_by(size.key_len)
+                        .collect();
+                    brute_force_single_byte_xor(&column, scorer)[0].key
+                })
+                .collect();
+            let key = minimal_period(&key).to_vec();
+            if !seen.insert(key.clone()) {
+                return None;
+            }
+            let plaintext = repeating_key_xor(ciphertext, &key);
+            Some(Solution {
+                score: scorer.score(&plaintext),
+                key,
+                plaintext,
+                key_size_score: size.score,
+            })
+        })
+        .collect();
+    solutions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cos_sim::CharFreq;
+    use crate::data::load_base64;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::fs;
+
+    const KEY: &[u8] = b"Terminator X: Bring the noise";
+
+    fn s1c6_ciphertext() -> Vec<u8> {
+        load_base64(concat!(env!("CARGO_MANIFEST_DIR"), "/s1c6_data.txt")).unwrap()
+    }
+
+    fn reference_freqs() -> CharFreq {
+        CharFreq::from_csv(include_str!("../good_charfreqs.csv").as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_s1c6() {
+        let ciphertext = s1c6_ciphertext();
+        for &metric in [KeySizeMetric::Hamming, KeySizeMetric::IndexOfCoincidence].iter() {
+            let options = BreakOptions {
+                metric,
+                ..BreakOptions::default()
+            };
+            let solutions = break_repeating_key_xor(&ciphertext, &reference_freqs(), &options);
+            assert_eq!(solutions[0].key, KEY, "{:?}", metric);
+            assert!(solutions[0]
+                .plaintext
+                .starts_with(b"I'm back and I'm ringin' the bell"));
+        }
+    }
+
+    #[test]
+    fn test_metrics_on_s1c6() {
+        let ciphertext = s1c6_ciphertext();
+        // Friedman's estimate is only a rough guide, and test_metric_accuracy covers it
+        for &metric in KeySizeMetric::ALL
+            .iter()
+            .filter(|&&m| m != KeySizeMetric::Friedman)
+        {
+            let options = BreakOptions {
+                metric,
+                ..BreakOptions::default()
+            };
+            let best = rank_key_sizes(&ciphertext, &options)[0].key_len;
+            assert_eq!(best, KEY.len(), "{:?}", metric);
+        }
+    }
+
+    /// How many of 13 random keys of length 2-38, used on Moby Dick excerpts as long as the
+    /// s1c6 ciphertext, have their length ranked in the top three
+    fn top_three_accuracy(metric: KeySizeMetric) -> usize {
+        let text = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/MobyDick.txt")).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let options = BreakOptions {
+            metric,
+            ..BreakOptions::default()
+        };
+        (2..=40)
+            .step_by(3)
+            .filter(|&key_len| {
+                let start = rng.gen_range(0..text.len() - 2876);
+                let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+                let ciphertext = repeating_key_xor(&text[start..start + 2876], &key);
+                rank_key_sizes(&ciphertext, &options)[..3]
+                    .iter()
+                    .any(|s| s.key_len == key_len)
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_metric_accuracy() {
+        for &metric in KeySizeMetric::ALL.iter() {
+            let accuracy = top_three_accuracy(metric);
+            // Friedman's estimate is usually close, but often not within one
+            let expected = match metric {
+                KeySizeMetric::Friedman => 4,
+                _ => 10,
+            };
+            assert!(accuracy >= expected, "{:?}: {}", metric, accuracy);
+        }
+    }
+
+    #[test]
+    fn test_rank_key_sizes() {
+        let ciphertext = s1c6_ciphertext();
+        let options = BreakOptions::default();
+        let ranked = rank_key_sizes(&ciphertext, &options);
+        assert_eq!(ranked.len(), 39);
+        assert!(ranked[..options.candidates]
+            .iter()
+            .any(|s| s.key_len == KEY.len()));
+    }
+
+    #[test]
+    fn test_short_ciphertext() {
+        let plaintext = b"Burning 'em, if you ain't quick and nimble";
+        let ciphertext = repeating_key_xor(plaintext, b"ICE");
+        let options = BreakOptions {
+            candidates: 5,
+            ..BreakOptions::default()
+        };
+        let ranked = rank_key_sizes(&ciphertext, &options);
+        assert!(ranked.iter().all(|s| s.key_len <= ciphertext.len() / 2));
+        // there's too little text to be sure of anything, but it must not panic
+        assert!(!break_repeating_key_xor(&ciphertext, &reference_freqs(), &options).is_empty());
+    }
+
+    #[test]
+    fn test_minimal_period() {
+        assert_eq!(minimal_period(b"ICEICEICE"), b"ICE");
+        assert_eq!(minimal_period(b"ICEICEIC"), b"ICEICEIC");
+        assert_eq!(minimal_period(b"A"), b"A");
+    }
+}
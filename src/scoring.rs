@@ -0,0 +1,195 @@
+//! Ways of ranking candidate plaintexts by how much they look like English. Every scorer follows
+//! the same convention: higher scores are more likely. The n-gram model itself lives in
+//! [`crate::ngram`].
+
+use crate::cos_sim::CharFreq;
+use crate::ngram::{normalize, NgramModel, Smoothing};
+
+/// How often we expect to see bytes outside the `CharFreq` alphabet in real text, e.g. accented
+/// Latin-1 letters. Used as the expected frequency of the out-of-alphabet bucket.
+const OUT_OF_ALPHABET_EXPECTED: f64 = 0.01;
+
+/// Scores raw bytes, so candidates that aren't valid UTF-8 or contain unusual bytes are
+/// penalized rather than thrown away
+pub trait Scorer {
+    fn score(&self, plaintext: &[u8]) -> f64;
+}
+
+/// Cosine similarity of single character frequencies against `self`, scaled by the fraction of
+/// bytes inside the alphabet
+impl Scorer for CharFreq {
+    fn score(&self, plaintext: &[u8]) -> f64 {
+        let (freq, out_of_alphabet) = CharFreq::from_bytes(plaintext);
+        if out_of_alphabet == plaintext.len() {
+            return 0.0;
+        }
+        let in_alphabet = 1.0 - out_of_alphabet as f64 / plaintext.len() as f64;
+        // cosine similarity ignores how many bytes were counted, so penalize the rest here
+        freq.cosine_similarity(self) * in_alphabet
+    }
+}
+
+/// Negated chi-squared statistic of single character frequencies against a reference, with bytes
+/// outside the alphabet as an extra bucket. Much harsher than cosine similarity on rare
+/// characters, which helps with short texts.
+pub struct ChiSquared(pub CharFreq);
+
+impl Scorer for ChiSquared {
+    fn score(&self, plaintext: &[u8]) -> f64 {
+        if plaintext.is_empty() {
+            return 0.0;
+        }
+        let (freq, out_of_alphabet) = CharFreq::from_bytes(plaintext);
+        let observed = out_of_alphabet as f64 / plaintext.len() as f64;
+        let extra = (observed - OUT_OF_ALPHABET_EXPECTED).powi(2) / OUT_OF_ALPHABET_EXPECTED;
+        -(freq.chi_squared(&self.0) + extra)
+    }
+}
+
+/// Needs no reference data. Scores the fraction of printable ASCII characters, averaged with the
+/// fraction of letters and spaces, so 0-1 inclusive.
+pub struct PrintableRatio;
+
+impl Scorer for PrintableRatio {
+    fn score(&self, plaintext: &[u8]) -> f64 {
+        if plaintext.is_empty() {
+            return 0.0;
+        }
+        let (printable, letters) = plaintext.iter().fold((0, 0), |(p, l), &b| {
+            let printable = matches!(b, b'\t' | b'\n' | b'\r' | 0x20..=0x7E);
+            let letter = b.is_ascii_alphabetic() || b == b' ';
+            (p + printable as usize, l + letter as usize)
+        });
+        (printable + letters) as f64 / (2 * plaintext.len()) as f64
+    }
+}
+
+/// Average log10-probability per character n-gram, from add-one smoothed counts over a corpus.
+/// Case is ignored. A thin wrapper over [`NgramModel`] for when a corpus and `n` are all there is.
+pub struct NgramScorer(NgramModel);
+
+impl NgramScorer {
+    pub fn from_corpus(corpus: &str, n: usize) -> NgramScorer {
+        NgramScorer(NgramModel::train(&normalize(corpus), n, Smoothing::LAPLACE))
+    }
+
+    pub fn n(&self) -> usize {
+        self.0.n()
+    }
+
+    pub fn model(&self) -> &NgramModel {
+        &self.0
+    }
+}
+
+impl From<NgramModel> for NgramScorer {
+    fn from(model: NgramModel) -> Self {
+        NgramScorer(model)
+    }
+}
+
+impl Scorer for NgramScorer {
+    fn score(&self, plaintext: &[u8]) -> f64 {
+        self.0.score(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force_single_byte_xor;
+    use hex_literal::hex;
+    use std::fs;
+
+    const S1C3: [u8; 34] =
+        hex!("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736");
+    const ENGLISH: &[u8] = b"Now is the winter of our discontent";
+    const GIBBERISH: &[u8] = b"Xq{ v` wkh zlqwhu ri rxu gl~frqwhqw";
+    const LATIN1: &[u8] = b"Caf\xe9 cr\xe8me br\xfbl\xe9e, served with a pot of tea";
+
+    fn reference_freqs() -> CharFreq {
+        CharFreq::from_csv(include_str!("../good_charfreqs.csv").as_bytes()).unwrap()
+    }
+
+    fn moby_dick() -> Vec<u8> {
+        let text =
+            fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/MobyDick.txt")).unwrap();
+        normalize(&text)
+    }
+
+    fn best_key<S: Scorer + ?Sized>(ciphertext: &[u8], scorer: &S) -> u8 {
+        brute_force_single_byte_xor(ciphertext, scorer)[0].key
+    }
+
+    #[test]
+    fn test_english_beats_gibberish() {
+        let corpus = moby_dick();
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(ChiSquared(reference_freqs())),
+            Box::new(PrintableRatio),
+            Box::new(NgramModel::train(&corpus, 2, Smoothing::AddK(0.5))),
+            Box::new(NgramModel::train(&corpus, 3, Smoothing::AddK(0.5))),
+        ];
+        for scorer in scorers.iter() {
+            assert!(scorer.score(ENGLISH) > scorer.score(GIBBERISH));
+        }
+    }
+
+    #[test]
+    fn test_s1c3() {
+        let corpus = moby_dick();
+        assert_eq!(best_key(&S1C3, &reference_freqs()), 0x58);
+        assert_eq!(best_key(&S1C3, &ChiSquared(reference_freqs())), 0x58);
+        assert_eq!(
+            best_key(&S1C3, &NgramModel::train(&corpus, 2, Smoothing::AddK(0.5))),
+            0x58
+        );
+        assert_eq!(
+            best_key(&S1C3, &NgramModel::train(&corpus, 3, Smoothing::AddK(0.5))),
+            0x58
+        );
+    }
+
+    #[test]
+    fn test_printable_ratio() {
+        assert_eq!(PrintableRatio.score(b"abc"), 1.0);
+        assert_eq!(PrintableRatio.score(b"1234"), 0.5);
+        assert_eq!(PrintableRatio.score(b"\x01\xff"), 0.0);
+    }
+
+    #[test]
+    fn test_out_of_alphabet_bytes() {
+        let ciphertext: Vec<u8> = LATIN1.iter().map(|b| b ^ 0x2a).collect();
+        let corpus = moby_dick();
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(reference_freqs()),
+            Box::new(ChiSquared(reference_freqs())),
+            Box::new(PrintableRatio),
+            Box::new(NgramModel::train(&corpus, 3, Smoothing::AddK(0.5))),
+        ];
+        for scorer in scorers.iter() {
+            assert_eq!(best_key(&ciphertext, scorer.as_ref()), 0x2a);
+        }
+    }
+
+    #[test]
+    fn test_short_text() {
+        let scorer = NgramScorer::from_corpus("the the the", 3);
+        assert_eq!(scorer.n(), 3);
+        // too short to hold an n-gram, so no better than a single unseen character
+        assert_eq!(scorer.score(b"th"), (1.0 / 128.0f64).log10());
+        assert!(scorer.score(b"the") > scorer.score(b"th"));
+        assert_eq!(
+            scorer.score(b"the"),
+            NgramScorer::from(NgramModel::train(b"THE THE THE", 3, Smoothing::LAPLACE))
+                .score(b"the")
+        );
+    }
+
+    #[test]
+    fn test_no_bytes_in_alphabet() {
+        let freqs = reference_freqs();
+        assert_eq!(freqs.score(b"\x01\x02\xff"), 0.0);
+        assert!(ChiSquared(freqs).score(b"\x01\x02\xff").is_finite());
+    }
+}